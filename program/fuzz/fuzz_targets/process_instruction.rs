@@ -0,0 +1,70 @@
+#![no_main]
+// arbitrary instruction data and account states through process_instruction,
+// malformed input has to come back as an error, any panic is a bug
+// run with `cargo fuzz run process_instruction` from program/
+#[path = "../../tests/common/input.rs"]
+mod input;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use input::{serialize_input, InputAccount};
+use pbv2::utils;
+use solana_program::{entrypoint, pubkey::Pubkey, system_program};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+#[derive(Arbitrary, Debug)]
+struct FuzzAccount {
+    dup: Option<u8>, // repeats an earlier account, like passing the same key twice
+    key: u8,         // picks from a small set of keys so the config and token programs come up often
+    owner: u8,       // same for the owner
+    is_signer: bool,
+    is_writable: bool,
+    lamports: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzInput {
+    instruction_data: Vec<u8>,
+    accounts: Vec<FuzzAccount>,
+}
+
+fuzz_target!(|input: FuzzInput| {
+    // dup indexes are a u8, like in the runtime's serialization
+    if input.accounts.len() > u8::MAX as usize {
+        return;
+    }
+    let keys = [
+        utils::find_config_address(&PROGRAM_ID).0,
+        spl_token::id(),
+        spl_token_2022::id(),
+        system_program::id(),
+        Pubkey::new_from_array([1; 32]),
+        Pubkey::new_from_array([2; 32]),
+        Pubkey::new_from_array([3; 32]),
+        Pubkey::new_from_array([4; 32]),
+    ];
+    let owners = [PROGRAM_ID, spl_token::id(), spl_token_2022::id(), system_program::id()];
+
+    let accounts: Vec<InputAccount> = input
+        .accounts
+        .into_iter()
+        .enumerate()
+        .map(|(i, account)| match (account.dup, i > 0) {
+            (Some(dup), true) => InputAccount::Dup(dup % i as u8),
+            _ => InputAccount::Account {
+                key: keys[account.key as usize % keys.len()],
+                owner: owners[account.owner as usize % owners.len()],
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+                lamports: account.lamports,
+                data: account.data,
+            },
+        })
+        .collect();
+
+    let mut buffer = serialize_input(&PROGRAM_ID, &accounts, &input.instruction_data);
+    let (program_id, infos, instruction_data) = unsafe { entrypoint::deserialize(buffer.as_mut_ptr() as *mut u8) };
+    let _ = pbv2::process_instruction(program_id, &infos, instruction_data);
+});
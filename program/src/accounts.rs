@@ -0,0 +1,631 @@
+// typed account lists for every instruction, in the order documented on PurebetInstruction.
+// parse checks signers, writable accounts, program ids and duplicates, and check_mint makes sure the
+// mint and every token account agree with the bet, so handlers only see accounts that passed both
+use crate::error::PurebetError;
+use crate::token;
+use crate::utils;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    system_program,
+};
+
+fn signer(account: &AccountInfo, error: PurebetError) -> ProgramResult {
+    if !account.is_signer {
+        msg!("{} isn't signing", account.key);
+        return Err(error.into());
+    }
+    Ok(())
+}
+
+fn writable(accounts: &[&AccountInfo]) -> ProgramResult {
+    for account in accounts {
+        if !account.is_writable {
+            msg!("{} isn't writable", account.key);
+            return Err(PurebetError::AccountNotWritable.into());
+        }
+    }
+    Ok(())
+}
+
+// the same key passed as two writable accounts would let one handler overwrite what the other wrote
+fn distinct(accounts: &[&AccountInfo]) -> ProgramResult {
+    for (i, account) in accounts.iter().enumerate() {
+        if accounts[i + 1..].iter().any(|other| other.key == account.key) {
+            msg!("{} is passed as more than one account", account.key);
+            return Err(PurebetError::DuplicateAccount.into());
+        }
+    }
+    Ok(())
+}
+
+fn token_program(account: &AccountInfo) -> ProgramResult {
+    if !token::is_token_program(account.key) {
+        msg!("Incorrect token program id");
+        return Err(PurebetError::IncorrectTokenProgram.into());
+    }
+    Ok(())
+}
+
+fn system(account: &AccountInfo) -> ProgramResult {
+    if *account.key != system_program::id() {
+        msg!("incorrect system program id");
+        return Err(PurebetError::IncorrectSystemProgram.into());
+    }
+    Ok(())
+}
+
+fn mint(mint: &AccountInfo, expected: [u8; 32], token_accounts: &[&AccountInfo]) -> ProgramResult {
+    if !utils::equal_wallets(mint.key.to_bytes(), expected) {
+        msg!("mint account doesn't match the bet");
+        return Err(PurebetError::MintMismatch.into());
+    }
+    for tok in token_accounts {
+        token::check_mint(tok, expected)?;
+    }
+    Ok(())
+}
+
+pub struct MakerAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub source: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub authority: &'a AccountInfo<'b>, // not necessarily the bettor if using free bet
+    pub bettor: &'a AccountInfo<'b>,
+    pub rent_payer: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub market: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> MakerAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = MakerAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            source: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            authority: next_account_info(accounts_iter)?,
+            bettor: next_account_info(accounts_iter)?,
+            rent_payer: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            market: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.authority, PurebetError::BettorNotSigner)?;
+        signer(parsed.rent_payer, PurebetError::RentPayerNotSigner)?;
+        let written = [parsed.bet, parsed.source, parsed.pool, parsed.rent_payer];
+        writable(&written)?;
+        distinct(&written)?;
+        token_program(parsed.token_program)?;
+        system(parsed.system_program)?;
+        Ok(parsed)
+    }
+
+    pub fn check_mint(&self, expected: [u8; 32]) -> ProgramResult {
+        mint(self.mint, expected, &[self.source, self.pool])
+    }
+}
+
+pub struct TakerAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub source: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub bettor: &'a AccountInfo<'b>, // cant use free bet in taker order, so bettor = authority
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub market: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> TakerAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = TakerAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            source: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            bettor: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            market: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.bettor, PurebetError::BettorNotSigner)?;
+        let written = [parsed.bet, parsed.source, parsed.pool];
+        writable(&written)?;
+        distinct(&written)?;
+        token_program(parsed.token_program)?;
+        Ok(parsed)
+    }
+
+    pub fn check_mint(&self, expected: [u8; 32]) -> ProgramResult {
+        mint(self.mint, expected, &[self.source, self.pool])
+    }
+}
+
+pub struct PartialTakerAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub source: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub bettor: &'a AccountInfo<'b>, // cant use free bet in taker order, so bettor = authority
+    pub rent_payer: &'a AccountInfo<'b>,
+    pub new_bet: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub maker_destination: &'a AccountInfo<'b>, // gets the rounding dust
+    pub pool_authority: &'a AccountInfo<'b>,
+    pub market: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> PartialTakerAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = PartialTakerAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            source: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            bettor: next_account_info(accounts_iter)?,
+            rent_payer: next_account_info(accounts_iter)?,
+            new_bet: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            maker_destination: next_account_info(accounts_iter)?,
+            pool_authority: next_account_info(accounts_iter)?,
+            market: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.bettor, PurebetError::BettorNotSigner)?;
+        signer(parsed.rent_payer, PurebetError::RentPayerNotSigner)?;
+        let written = [
+            parsed.bet,
+            parsed.source,
+            parsed.pool,
+            parsed.rent_payer,
+            parsed.new_bet,
+            parsed.maker_destination,
+        ];
+        writable(&written)?;
+        distinct(&written)?;
+        token_program(parsed.token_program)?;
+        system(parsed.system_program)?;
+        Ok(parsed)
+    }
+
+    pub fn check_mint(&self, expected: [u8; 32]) -> ProgramResult {
+        mint(self.mint, expected, &[self.source, self.pool, self.maker_destination])
+    }
+}
+
+// Cancel and Refund
+pub struct CancelAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub destination: &'a AccountInfo<'b>,
+    pub signer: &'a AccountInfo<'b>, // bettor canceling or admin refunding
+    pub rent_payer: &'a AccountInfo<'b>,
+    pub pool_authority: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub delay: Option<&'a AccountInfo<'b>>, // only for to_aggregate bets
+}
+
+impl<'a, 'b> CancelAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = CancelAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            destination: next_account_info(accounts_iter)?,
+            signer: next_account_info(accounts_iter)?,
+            rent_payer: next_account_info(accounts_iter)?,
+            pool_authority: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            delay: accounts_iter.next(),
+        };
+        signer(parsed.signer, PurebetError::BettorNotSigner)?;
+        let written = [parsed.bet, parsed.pool, parsed.destination, parsed.rent_payer];
+        writable(&written)?;
+        distinct(&written)?;
+        if let Some(delay) = parsed.delay {
+            distinct(&[parsed.bet, delay])?;
+        }
+        token_program(parsed.token_program)?;
+        Ok(parsed)
+    }
+
+    pub fn check_mint(&self, expected: [u8; 32]) -> ProgramResult {
+        mint(self.mint, expected, &[self.pool, self.destination])
+    }
+}
+
+pub struct SetDelayAccounts<'a, 'b> {
+    pub delay: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetDelayAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = SetDelayAccounts {
+            delay: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        writable(&[parsed.delay])?;
+        Ok(parsed)
+    }
+}
+
+pub struct GradeAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub destination: &'a AccountInfo<'b>, // winner's token account
+    pub admin: &'a AccountInfo<'b>,
+    pub rent_payer: &'a AccountInfo<'b>,
+    pub pool_authority: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub market: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> GradeAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = GradeAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            destination: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            rent_payer: next_account_info(accounts_iter)?,
+            pool_authority: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            market: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        let written = [parsed.bet, parsed.pool, parsed.destination, parsed.rent_payer];
+        writable(&written)?;
+        distinct(&written)?;
+        token_program(parsed.token_program)?;
+        Ok(parsed)
+    }
+
+    pub fn check_mint(&self, expected: [u8; 32]) -> ProgramResult {
+        mint(self.mint, expected, &[self.pool, self.destination])
+    }
+}
+
+pub struct PushAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub destination0: &'a AccountInfo<'b>,
+    pub destination1: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub rent_payer: &'a AccountInfo<'b>,
+    pub pool_authority: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub market: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> PushAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = PushAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            destination0: next_account_info(accounts_iter)?,
+            destination1: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            rent_payer: next_account_info(accounts_iter)?,
+            pool_authority: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            market: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        writable(&[parsed.bet, parsed.pool, parsed.destination0, parsed.destination1, parsed.rent_payer])?;
+        // both sides can be refunded to the same token account, e.g. a free bet's rent payer that took the other side
+        distinct(&[parsed.bet, parsed.pool, parsed.destination0, parsed.rent_payer])?;
+        distinct(&[parsed.bet, parsed.pool, parsed.destination1, parsed.rent_payer])?;
+        token_program(parsed.token_program)?;
+        Ok(parsed)
+    }
+
+    pub fn check_mint(&self, expected: [u8; 32]) -> ProgramResult {
+        mint(self.mint, expected, &[self.pool, self.destination0, self.destination1])
+    }
+}
+
+pub struct InitConfigAccounts<'a, 'b> {
+    pub config: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> InitConfigAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = InitConfigAccounts {
+            config: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        let written = [parsed.config, parsed.admin];
+        writable(&written)?;
+        distinct(&written)?;
+        system(parsed.system_program)?;
+        Ok(parsed)
+    }
+}
+
+pub struct UpdateConfigAccounts<'a, 'b> {
+    pub config: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> UpdateConfigAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = UpdateConfigAccounts {
+            config: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        writable(&[parsed.config])?;
+        Ok(parsed)
+    }
+}
+
+pub struct SetPoolAccounts<'a, 'b> {
+    pub config: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub vault: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SetPoolAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = SetPoolAccounts {
+            config: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            vault: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        writable(&[parsed.config])?;
+        Ok(parsed)
+    }
+}
+
+pub struct InitVaultAccounts<'a, 'b> {
+    pub config: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub vault: &'a AccountInfo<'b>,
+    pub pool_authority: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> InitVaultAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = InitVaultAccounts {
+            config: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            vault: next_account_info(accounts_iter)?,
+            pool_authority: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        let written = [parsed.config, parsed.admin, parsed.vault];
+        writable(&written)?;
+        distinct(&written)?;
+        token_program(parsed.token_program)?;
+        system(parsed.system_program)?;
+        // the vault is created under whichever token program owns the mint
+        if parsed.mint.owner != parsed.token_program.key {
+            msg!("mint isn't owned by the token program passed");
+            return Err(PurebetError::IncorrectTokenProgram.into());
+        }
+        Ok(parsed)
+    }
+}
+
+pub struct InitMarketAccounts<'a, 'b> {
+    pub market: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> InitMarketAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = InitMarketAccounts {
+            market: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        let written = [parsed.market, parsed.admin];
+        writable(&written)?;
+        distinct(&written)?;
+        system(parsed.system_program)?;
+        Ok(parsed)
+    }
+}
+
+pub struct UpdateMarketAccounts<'a, 'b> {
+    pub market: &'a AccountInfo<'b>,
+    pub admin: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> UpdateMarketAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = UpdateMarketAccounts {
+            market: next_account_info(accounts_iter)?,
+            admin: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.admin, PurebetError::NotAdmin)?;
+        writable(&[parsed.market])?;
+        Ok(parsed)
+    }
+}
+
+pub struct PostResultAccounts<'a, 'b> {
+    pub market: &'a AccountInfo<'b>,
+    pub oracle: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> PostResultAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = PostResultAccounts {
+            market: next_account_info(accounts_iter)?,
+            oracle: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.oracle, PurebetError::NotOracle)?;
+        writable(&[parsed.market])?;
+        Ok(parsed)
+    }
+}
+
+pub struct PostScoreAccounts<'a, 'b> {
+    pub score: &'a AccountInfo<'b>,
+    pub oracle: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> PostScoreAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = PostScoreAccounts {
+            score: next_account_info(accounts_iter)?,
+            oracle: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+        };
+        signer(parsed.oracle, PurebetError::NotOracle)?;
+        let written = [parsed.score, parsed.oracle];
+        writable(&written)?;
+        distinct(&written)?;
+        system(parsed.system_program)?;
+        Ok(parsed)
+    }
+}
+
+pub struct GradeMarketAccounts<'a, 'b> {
+    pub market: &'a AccountInfo<'b>,
+    pub score: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> GradeMarketAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = GradeMarketAccounts {
+            market: next_account_info(accounts_iter)?,
+            score: next_account_info(accounts_iter)?,
+        };
+        // no signer, the oracle already vouched for the score
+        writable(&[parsed.market])?;
+        Ok(parsed)
+    }
+}
+
+pub struct SettleAccounts<'a, 'b> {
+    pub bet: &'a AccountInfo<'b>,
+    pub token_program: &'a AccountInfo<'b>,
+    pub pool: &'a AccountInfo<'b>,
+    pub destination0: &'a AccountInfo<'b>,
+    pub destination1: &'a AccountInfo<'b>,
+    pub rent_payer: &'a AccountInfo<'b>,
+    pub pool_authority: &'a AccountInfo<'b>,
+    pub config: &'a AccountInfo<'b>,
+    pub mint: &'a AccountInfo<'b>,
+    pub market: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SettleAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = SettleAccounts {
+            bet: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?,
+            pool: next_account_info(accounts_iter)?,
+            destination0: next_account_info(accounts_iter)?,
+            destination1: next_account_info(accounts_iter)?,
+            rent_payer: next_account_info(accounts_iter)?,
+            pool_authority: next_account_info(accounts_iter)?,
+            config: next_account_info(accounts_iter)?,
+            mint: next_account_info(accounts_iter)?,
+            market: next_account_info(accounts_iter)?,
+        };
+        // no signer, anyone can crank a bet once its market's result is posted
+        writable(&[parsed.bet, parsed.pool, parsed.destination0, parsed.destination1, parsed.rent_payer])?;
+        distinct(&[parsed.bet, parsed.pool, parsed.destination0, parsed.rent_payer])?;
+        distinct(&[parsed.bet, parsed.pool, parsed.destination1, parsed.rent_payer])?;
+        token_program(parsed.token_program)?;
+        Ok(parsed)
+    }
+
+    // only the destinations that get paid are checked, a loser's token account may not exist anymore
+    pub fn check_mint(&self, expected: [u8; 32], paid: &[&AccountInfo]) -> ProgramResult {
+        mint(self.mint, expected, &[self.pool])?;
+        mint(self.mint, expected, paid)
+    }
+}
+
+pub struct MigrateAccounts<'a, 'b> {
+    pub account: &'a AccountInfo<'b>,
+    pub payer: &'a AccountInfo<'b>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub config: Option<&'a AccountInfo<'b>>, // only for bets whose free bet side has to be looked up
+}
+
+impl<'a, 'b> MigrateAccounts<'a, 'b> {
+    pub fn parse(accounts: &'a [AccountInfo<'b>]) -> Result<Self, ProgramError> {
+        let accounts_iter = &mut accounts.iter();
+        let parsed = MigrateAccounts {
+            account: next_account_info(accounts_iter)?,
+            payer: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?,
+            config: accounts_iter.next(),
+        };
+        signer(parsed.payer, PurebetError::RentPayerNotSigner)?;
+        let written = [parsed.account, parsed.payer];
+        writable(&written)?;
+        distinct(&written)?;
+        system(parsed.system_program)?;
+        Ok(parsed)
+    }
+}
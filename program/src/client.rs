@@ -0,0 +1,503 @@
+// builders for every instruction so integrations don't hand assemble account lists or instruction data
+// token_program is spl_token::id() or spl_token_2022::id(), whichever owns the pool's mint,
+// builders that pass the pool pda fail if the pool's bump doesn't derive one
+use crate::instruction::{BetIds, ConfigArgs, EventIds, PurebetInstruction};
+use crate::utils::{self, MarketResult, MarketStatus, Pool};
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::{Pubkey, PubkeyError},
+    system_program,
+};
+
+pub fn config_address(program_id: &Pubkey) -> Pubkey {
+    utils::find_config_address(program_id).0
+}
+
+// pda that owns the pool's vault and signs transfers out of it, from the bump stored in the config
+// so a pool read off chain with a bump that doesn't derive a pda is an error rather than a panic
+pub fn pool_authority(program_id: &Pubkey, pool: &Pool) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_program_address(&[b"pool", &pool.mint, &[pool.bump]], program_id)
+}
+
+// vault InitVault creates for mint
+pub fn vault_address(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    utils::find_vault_address(mint, program_id).0
+}
+
+// market pda every bet on ids references
+pub fn market_address(program_id: &Pubkey, ids: &BetIds) -> Pubkey {
+    utils::find_market_address(ids, program_id).0
+}
+
+// score pda the oracle posts an event period's final score to
+pub fn score_address(program_id: &Pubkey, event: &EventIds) -> Pubkey {
+    utils::find_score_address(event, program_id).0
+}
+
+// bet pda for a maker (or partial taker) wallet and nonce
+pub fn bet_address(program_id: &Pubkey, ids: &BetIds, maker: &Pubkey, nonce: u64) -> Pubkey {
+    utils::find_bet_address(ids, maker, nonce, program_id).0
+}
+
+fn build(program_id: &Pubkey, data: PurebetInstruction, accounts: Vec<AccountMeta>) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        // serializing into a vec can't fail
+        data: data.try_to_vec().unwrap(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn maker(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    source: &Pubkey,
+    authority: &Pubkey,
+    bettor: &Pubkey,
+    rent_payer: &Pubkey,
+    ids: BetIds,
+    stake0: u64,
+    stake1: u64,
+    side: u8,
+    to_aggregate: bool,
+    nonce: u64,
+) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::InitBet {
+            ids,
+            stake0,
+            stake1,
+            side,
+            to_aggregate,
+            nonce,
+        },
+        vec![
+            AccountMeta::new(bet_address(program_id, &ids, bettor, nonce), false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*bettor, false),
+            AccountMeta::new(*rent_payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+            AccountMeta::new_readonly(market_address(program_id, &ids), false),
+        ],
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn taker(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    source: &Pubkey,
+    bettor: &Pubkey,
+    ids: BetIds,
+    stake0: u64,
+    stake1: u64,
+    side: u8,
+) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::Take {
+            ids,
+            stake0,
+            stake1,
+            side,
+        },
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+            AccountMeta::new_readonly(*bettor, true),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+            AccountMeta::new_readonly(market_address(program_id, &ids), false),
+        ],
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn partial_taker(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    source: &Pubkey,
+    bettor: &Pubkey,
+    rent_payer: &Pubkey,
+    maker_destination: &Pubkey,
+    ids: BetIds,
+    stake0: u64,
+    stake1: u64,
+    side: u8,
+    nonce: u64,
+    max_slippage_bps: u16,
+) -> Result<Instruction, PubkeyError> {
+    Ok(build(
+        program_id,
+        PurebetInstruction::PartialTake {
+            ids,
+            stake0,
+            stake1,
+            side,
+            nonce,
+            max_slippage_bps,
+        },
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+            AccountMeta::new_readonly(*bettor, true),
+            AccountMeta::new(*rent_payer, true),
+            AccountMeta::new(bet_address(program_id, &ids, bettor, nonce), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+            AccountMeta::new(*maker_destination, false),
+            AccountMeta::new_readonly(pool_authority(program_id, pool)?, false),
+            AccountMeta::new_readonly(market_address(program_id, &ids), false),
+        ],
+    ))
+}
+
+// delay_acc has to be passed when canceling a to_aggregate bet
+#[allow(clippy::too_many_arguments)]
+pub fn cancel(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    destination: &Pubkey,
+    bettor: &Pubkey,
+    rent_payer: &Pubkey,
+    delay_acc: Option<&Pubkey>,
+    ids: BetIds,
+    side: u8,
+) -> Result<Instruction, PubkeyError> {
+    Ok(build(
+        program_id,
+        PurebetInstruction::Cancel { ids, side },
+        cancel_accounts(program_id, pool, token_program, bet, destination, bettor, rent_payer, delay_acc)?,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn refund(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    destination: &Pubkey,
+    admin: &Pubkey,
+    rent_payer: &Pubkey,
+    delay_acc: Option<&Pubkey>,
+    ids: BetIds,
+    side: u8,
+) -> Result<Instruction, PubkeyError> {
+    Ok(build(
+        program_id,
+        PurebetInstruction::Refund { ids, side },
+        cancel_accounts(program_id, pool, token_program, bet, destination, admin, rent_payer, delay_acc)?,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cancel_accounts(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    destination: &Pubkey,
+    signer: &Pubkey,
+    rent_payer: &Pubkey,
+    delay_acc: Option<&Pubkey>,
+) -> Result<Vec<AccountMeta>, PubkeyError> {
+    let mut accounts = vec![
+        AccountMeta::new(*bet, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new(*rent_payer, false),
+        AccountMeta::new_readonly(pool_authority(program_id, pool)?, false),
+        AccountMeta::new_readonly(config_address(program_id), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+    ];
+    if let Some(delay_acc) = delay_acc {
+        accounts.push(AccountMeta::new_readonly(*delay_acc, false));
+    }
+    Ok(accounts)
+}
+
+pub fn set_delay(program_id: &Pubkey, delay_acc: &Pubkey, admin: &Pubkey, seconds: u8) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::SetDelay { seconds },
+        vec![
+            AccountMeta::new(*delay_acc, false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(config_address(program_id), false),
+        ],
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn grade(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    destination: &Pubkey,
+    admin: &Pubkey,
+    rent_payer: &Pubkey,
+    ids: BetIds,
+    winner: u8,
+) -> Result<Instruction, PubkeyError> {
+    Ok(build(
+        program_id,
+        PurebetInstruction::Grade { ids, winner },
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*rent_payer, false),
+            AccountMeta::new_readonly(pool_authority(program_id, pool)?, false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+            AccountMeta::new_readonly(market_address(program_id, &ids), false),
+        ],
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn push(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    destination0: &Pubkey,
+    destination1: &Pubkey,
+    admin: &Pubkey,
+    rent_payer: &Pubkey,
+    ids: BetIds,
+) -> Result<Instruction, PubkeyError> {
+    Ok(build(
+        program_id,
+        PurebetInstruction::Push { ids },
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+            AccountMeta::new(*destination0, false),
+            AccountMeta::new(*destination1, false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(*rent_payer, false),
+            AccountMeta::new_readonly(pool_authority(program_id, pool)?, false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+            AccountMeta::new_readonly(market_address(program_id, &ids), false),
+        ],
+    ))
+}
+
+pub fn init_config(program_id: &Pubkey, admin: &Pubkey, args: ConfigArgs) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::InitConfig { args },
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn update_config(program_id: &Pubkey, admin: &Pubkey, args: ConfigArgs) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::UpdateConfig { args },
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new_readonly(*admin, true),
+        ],
+    )
+}
+
+pub fn set_pool(program_id: &Pubkey, admin: &Pubkey, vault: &Pubkey, bump: u8) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::SetPool { bump },
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(*vault, false),
+        ],
+    )
+}
+
+pub fn migrate_bet(program_id: &Pubkey, bet: &Pubkey, payer: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::MigrateBet,
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+        ],
+    )
+}
+
+pub fn init_vault(program_id: &Pubkey, admin: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::InitVault,
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(vault_address(program_id, mint), false),
+            AccountMeta::new_readonly(utils::find_pool_authority(mint, program_id).0, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn init_market(program_id: &Pubkey, admin: &Pubkey, ids: BetIds, start_time: u64) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::InitMarket { ids, start_time },
+        vec![
+            AccountMeta::new(market_address(program_id, &ids), false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn update_market(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    ids: BetIds,
+    status: MarketStatus,
+    start_time: u64,
+) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::UpdateMarket {
+            ids,
+            status,
+            start_time,
+        },
+        vec![
+            AccountMeta::new(market_address(program_id, &ids), false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(config_address(program_id), false),
+        ],
+    )
+}
+
+pub fn migrate_config(program_id: &Pubkey, payer: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::MigrateConfig,
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn set_oracle(program_id: &Pubkey, admin: &Pubkey, oracle: &Pubkey) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::SetOracle {
+            oracle: oracle.to_bytes(),
+        },
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new_readonly(*admin, true),
+        ],
+    )
+}
+
+pub fn post_result(program_id: &Pubkey, oracle: &Pubkey, ids: BetIds, result: MarketResult) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::PostResult { ids, result },
+        vec![
+            AccountMeta::new(market_address(program_id, &ids), false),
+            AccountMeta::new_readonly(*oracle, true),
+            AccountMeta::new_readonly(config_address(program_id), false),
+        ],
+    )
+}
+
+// the loser's destination isn't read, any writable account other than the winner's destination will do
+#[allow(clippy::too_many_arguments)]
+pub fn settle(
+    program_id: &Pubkey,
+    pool: &Pool,
+    token_program: &Pubkey,
+    bet: &Pubkey,
+    destination0: &Pubkey,
+    destination1: &Pubkey,
+    rent_payer: &Pubkey,
+    ids: BetIds,
+) -> Result<Instruction, PubkeyError> {
+    Ok(build(
+        program_id,
+        PurebetInstruction::Settle { ids },
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new(Pubkey::new_from_array(pool.vault), false),
+            AccountMeta::new(*destination0, false),
+            AccountMeta::new(*destination1, false),
+            AccountMeta::new(*rent_payer, false),
+            AccountMeta::new_readonly(pool_authority(program_id, pool)?, false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pool.mint), false),
+            AccountMeta::new_readonly(market_address(program_id, &ids), false),
+        ],
+    ))
+}
+
+pub fn post_score(program_id: &Pubkey, oracle: &Pubkey, event: EventIds, home: u32, away: u32) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::PostScore { event, home, away },
+        vec![
+            AccountMeta::new(score_address(program_id, &event), false),
+            AccountMeta::new(*oracle, true),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+pub fn grade_market(program_id: &Pubkey, ids: BetIds) -> Instruction {
+    build(
+        program_id,
+        PurebetInstruction::GradeMarket { ids },
+        vec![
+            AccountMeta::new(market_address(program_id, &ids), false),
+            AccountMeta::new_readonly(score_address(program_id, &ids.event_ids()), false),
+        ],
+    )
+}
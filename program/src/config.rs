@@ -0,0 +1,145 @@
+use crate::accounts::{InitConfigAccounts, InitVaultAccounts, SetPoolAccounts, UpdateConfigAccounts};
+use crate::error::PurebetError;
+use crate::instruction::ConfigArgs;
+use crate::token;
+use crate::utils;
+use crate::utils::ProgramAccount;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+pub fn init(accounts: &[AccountInfo], program_id: &Pubkey, args: ConfigArgs) -> ProgramResult {
+    let InitConfigAccounts { config, admin, system_program } = InitConfigAccounts::parse(accounts)?;
+
+    // the config can only be created once, by the admin key compiled into the program
+    if !utils::equal_wallets(admin.key.to_bytes(), utils::ADMIN) {
+        msg!("only the admin key can create the config acc");
+        return Err(PurebetError::NotAdmin.into());
+    }
+    let (address, bump) = utils::find_config_address(program_id);
+    if address != *config.key {
+        msg!("incorrect config account");
+        return Err(PurebetError::IncorrectConfig.into());
+    }
+    if config.owner == program_id {
+        msg!("config account already initialized");
+        return Err(PurebetError::ConfigAlreadyInitialized.into());
+    }
+    utils::create_pda_account(config, admin, system_program, program_id, utils::CONFIG_LEN, &[b"config", &[bump]])?;
+
+    let config_acc = utils::Config {
+        is_initialized: true,
+        admin: args.admin,
+        market_makers: args.market_makers,
+        odds_tolerance_bps: args.odds_tolerance_bps,
+        ..Default::default()
+    };
+    config_acc.pack(&mut config.data.borrow_mut())?;
+    Ok(())
+}
+
+pub fn update(accounts: &[AccountInfo], program_id: &Pubkey, args: ConfigArgs) -> ProgramResult {
+    let UpdateConfigAccounts { config, admin } = UpdateConfigAccounts::parse(accounts)?;
+
+    let mut config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_admin(admin.key.to_bytes()) {
+        msg!("only the admin key can update the config acc");
+        return Err(PurebetError::NotAdmin.into());
+    }
+
+    config_acc.admin = args.admin;
+    config_acc.market_makers = args.market_makers;
+    config_acc.odds_tolerance_bps = args.odds_tolerance_bps;
+    config_acc.pack(&mut config.data.borrow_mut())?;
+    Ok(())
+}
+
+pub fn set_oracle(accounts: &[AccountInfo], program_id: &Pubkey, oracle: [u8; 32]) -> ProgramResult {
+    let UpdateConfigAccounts { config, admin } = UpdateConfigAccounts::parse(accounts)?;
+
+    let mut config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_admin(admin.key.to_bytes()) {
+        msg!("only the admin key can set the oracle");
+        return Err(PurebetError::NotAdmin.into());
+    }
+
+    config_acc.oracle = oracle;
+    config_acc.pack(&mut config.data.borrow_mut())?;
+    Ok(())
+}
+
+// whitelist a mint by registering its vault, or move an already whitelisted mint to a new vault
+pub fn set_pool(accounts: &[AccountInfo], program_id: &Pubkey, bump: u8) -> ProgramResult {
+    let SetPoolAccounts { config, admin, vault } = SetPoolAccounts::parse(accounts)?;
+
+    let mut config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_admin(admin.key.to_bytes()) {
+        msg!("only the admin key can update the config acc");
+        return Err(PurebetError::NotAdmin.into());
+    }
+
+    // the vault has to be owned by the mint's pool pda, which signs transfers out of it
+    let mint = token::mint_of(vault)?;
+    let pool_authority = Pubkey::create_program_address(&[b"pool", &mint, &[bump]], program_id)
+        .map_err(|_| PurebetError::IncorrectPoolAuthority)?;
+    if !token::are_paired(pool_authority.to_bytes(), vault)? {
+        msg!("vault isn't owned by the pool pda");
+        return Err(PurebetError::IncorrectPoolAuthority.into());
+    }
+
+    let pool = utils::Pool {
+        mint,
+        vault: vault.key.to_bytes(),
+        bump,
+    };
+    config_acc.set_pool(pool)?;
+    config_acc.pack(&mut config.data.borrow_mut())?;
+    Ok(())
+}
+
+// create the mint's vault at its pda, owned by the canonical pool pda, and whitelist the mint with it
+pub fn init_vault(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let InitVaultAccounts {
+        config,
+        admin,
+        vault,
+        pool_authority,
+        mint,
+        token_program,
+        system_program,
+    } = InitVaultAccounts::parse(accounts)?;
+
+    let mut config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_admin(admin.key.to_bytes()) {
+        msg!("only the admin key can update the config acc");
+        return Err(PurebetError::NotAdmin.into());
+    }
+
+    let (authority, bump) = utils::find_pool_authority(mint.key, program_id);
+    if authority != *pool_authority.key {
+        msg!("pool authority isn't the mint's pool pda");
+        return Err(PurebetError::IncorrectPoolAuthority.into());
+    }
+    let (vault_address, vault_bump) = utils::find_vault_address(mint.key, program_id);
+    if vault_address != *vault.key {
+        msg!("vault isn't the mint's vault pda");
+        return Err(PurebetError::IncorrectPool.into());
+    }
+
+    let space = token::vault_len(mint)?;
+    let vault_seeds: &[&[u8]] = &[b"vault", mint.key.as_ref(), &[vault_bump]];
+    utils::create_pda_account(vault, admin, system_program, token_program.key, space, vault_seeds)?;
+    token::init_vault(vault, mint, pool_authority.key, token_program)?;
+
+    let pool = utils::Pool {
+        mint: mint.key.to_bytes(),
+        vault: vault.key.to_bytes(),
+        bump,
+    };
+    config_acc.set_pool(pool)?;
+    config_acc.pack(&mut config.data.borrow_mut())?;
+    Ok(())
+}
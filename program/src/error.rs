@@ -0,0 +1,167 @@
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+// returned to clients as ProgramError::Custom(code), the codes are stable:
+// never renumber or reuse a variant, only append new ones at the end
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PurebetError {
+    /// 0: instruction data doesn't deserialize into a PurebetInstruction
+    #[error("Invalid instruction")]
+    InvalidInstruction = 0,
+    /// 1: bet or delay account isn't owned by this program
+    #[error("Account doesn't belong to this program id")]
+    IncorrectOwner = 1,
+    /// 2: the instruction doesn't apply to an open, partially matched or matched bet
+    #[error("Instruction doesn't apply to the current state of the bet")]
+    InvalidBetState = 2,
+    /// 3: token account receiving or paying stakes isn't the pool
+    #[error("Incorrect pool")]
+    IncorrectPool = 3,
+    /// 4: token program passed isn't the spl token or token-2022 program
+    #[error("Incorrect token program id")]
+    IncorrectTokenProgram = 4,
+    /// 5: id information in the instruction doesn't match the bet
+    #[error("Id information of bet and instruction data don't match")]
+    IdMismatch = 5,
+    /// 6: starting a bet in a bet account that is already in use
+    #[error("Trying to start bet in non empty bet account")]
+    BetAccountNotEmpty = 6,
+    /// 7: taking a side of a bet that already has a bettor
+    #[error("Trying to match bet on side that has already been matched")]
+    SideAlreadyMatched = 7,
+    /// 8: taker's stakes give better odds than the maker offered
+    #[error("Bettor wants odds that are too high")]
+    OddsTooHigh = 8,
+    /// 9: only the market maker can take free bets and bets marked for aggregation
+    #[error("Not authorized to take a free bet or a bet marked for aggregation")]
+    NotMarketMaker = 9,
+    /// 10: canceling the side of the bet that has no bettor
+    #[error("Trying to cancel wrong side of bet")]
+    WrongSide = 10,
+    /// 11: side or winning side isn't 0 or 1
+    #[error("Side must be 0 or 1")]
+    InvalidSide = 11,
+    /// 12: bettor didn't sign
+    #[error("Bettor isn't signing")]
+    BettorNotSigner = 12,
+    /// 13: signer isn't the bettor of this bet
+    #[error("Not correct bettor canceling")]
+    NotBettor = 13,
+    /// 14: admin only instruction not signed by the admin
+    #[error("Only the admin key can do this")]
+    NotAdmin = 14,
+    /// 15: destination token account isn't owned by who is being paid
+    #[error("Wrong associated token account")]
+    WrongTokenAccount = 15,
+    /// 16: rent payer passed isn't the bet's rent payer
+    #[error("Rent can only be refunded to the original rent payer")]
+    WrongRentPayer = 16,
+    /// 17: delay account was never set by the admin
+    #[error("Cancelation delay account isn't set")]
+    InvalidDelayAccount = 17,
+    /// 18: to_aggregate bet canceled before the cancelation delay passed
+    #[error("Too early to cancel")]
+    TooEarlyToCancel = 18,
+    /// 19: stake, lamport or time arithmetic overflowed or underflowed
+    #[error("Arithmetic overflow")]
+    ArithmeticOverflow = 19,
+    /// 20: bet account isn't the pda of its ids, maker wallet and nonce
+    #[error("Bet account isn't derived from its ids, maker and nonce")]
+    WrongBetAddress = 20,
+    /// 21: rent payer has to sign to fund a new bet account
+    #[error("Rent payer isn't signing")]
+    RentPayerNotSigner = 21,
+    /// 22: config account isn't this program's initialized config pda
+    #[error("Incorrect config account")]
+    IncorrectConfig = 22,
+    /// 23: config account was already initialized
+    #[error("Config account already initialized")]
+    ConfigAlreadyInitialized = 23,
+    /// 24: pool token account isn't owned by the pool pda derived from the bump
+    #[error("Pool token account isn't owned by the pool pda")]
+    IncorrectPoolAuthority = 24,
+    /// 25: token account mint doesn't match the pool or bet mint
+    #[error("Token account mint doesn't match the bet")]
+    MintMismatch = 25,
+    /// 26: no pool has been set for the mint
+    #[error("Mint isn't whitelisted")]
+    MintNotWhitelisted = 26,
+    /// 27: every pool slot in the config is used
+    #[error("No free pool slots left in the config")]
+    PoolsFull = 27,
+    /// 28: partial fill takes more than the open side of the bet
+    #[error("Partial fill is larger than the open stake")]
+    FillTooLarge = 28,
+    /// 29: odds tolerance in the config or a taker's max slippage is more than 10000 bps
+    #[error("Odds tolerance and slippage can't be more than 10000 bps")]
+    InvalidOddsTolerance = 29,
+    /// 30: full taker's stake is less than the open side of the bet
+    #[error("Taker stake doesn't cover the open side of the bet")]
+    InsufficientStake = 30,
+    /// 31: program account's type byte isn't the kind of account the instruction expects
+    #[error("Account is the wrong type")]
+    WrongAccountType = 31,
+    /// 32: program account was written with a layout version this program can't read
+    #[error("Unsupported account version")]
+    WrongAccountVersion = 32,
+    /// 33: account the instruction writes to wasn't passed as writable
+    #[error("Account isn't writable")]
+    AccountNotWritable = 33,
+    /// 34: the same account was passed in two places that have to be different accounts
+    #[error("Account passed more than once")]
+    DuplicateAccount = 34,
+    /// 35: account passed as the system program isn't the system program
+    #[error("Incorrect system program id")]
+    IncorrectSystemProgram = 35,
+    /// 36: market is suspended, closed or settled
+    #[error("Market isn't open")]
+    MarketNotOpen = 36,
+    /// 37: market's start time has passed, no new orders or matches
+    #[error("Market has already started")]
+    MarketStarted = 37,
+    /// 38: status change a market can't make, settled is only set by the result and is final
+    #[error("Invalid market status change")]
+    InvalidMarketStatus = 38,
+    /// 39: market account isn't the pda of its ids
+    #[error("Market account isn't derived from its ids")]
+    IncorrectMarket = 39,
+    /// 40: creating a market that already exists
+    #[error("Market already exists")]
+    MarketAlreadyInitialized = 40,
+    /// 41: market result not posted by the config's oracle
+    #[error("Only the oracle key can post results")]
+    NotOracle = 41,
+    /// 42: settling a bet before its market's result is posted
+    #[error("Market result hasn't been posted")]
+    MarketNotSettled = 42,
+    /// 43: score account isn't the pda of its event ids
+    #[error("Score account isn't derived from its event")]
+    IncorrectScore = 43,
+    /// 44: posting a score for an event period that already has one
+    #[error("Score already posted")]
+    ScoreAlreadyPosted = 44,
+    /// 45: market's mkt or player can't be graded from the final score
+    #[error("Market can't be graded from a score")]
+    UngradableMarket = 45,
+    /// 46: mkt or player in the ids isn't a valid market or player code
+    #[error("Invalid market or player code")]
+    InvalidMarketCode = 46,
+    /// 47: maker or partial taker order with no stake
+    #[error("Stake must be more than zero")]
+    ZeroStake = 47,
+    /// 48: taker or partial taker order on a bet with nothing left open on its side
+    #[error("Bet has no open stake left to fill")]
+    NoOpenStake = 48,
+    /// 49: admin grading or pushing a bet against its market's posted result
+    #[error("Market's posted result doesn't match")]
+    ResultMismatch = 49,
+    /// 50: anything but void posted for a market graded from the score, or a half win for one that isn't a quarter line
+    #[error("Result isn't possible for this market")]
+    InvalidMarketResult = 50,
+}
+
+impl From<PurebetError> for ProgramError {
+    fn from(e: PurebetError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
@@ -0,0 +1,203 @@
+use crate::error::PurebetError;
+use crate::odds::MAX_BPS;
+use crate::utils::{MarketResult, MarketStatus, MAX_MARKET_MAKERS};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{msg, program_error::ProgramError};
+
+// id information that every bet instruction carries, same fields and order as the start of BetAcc
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BetIds {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+    pub mkt: u16,
+    pub player: u32,
+}
+
+impl BetIds {
+    pub fn event_ids(&self) -> EventIds {
+        EventIds {
+            sport: self.sport,
+            league: self.league,
+            event: self.event,
+            period: self.period,
+        }
+    }
+}
+
+// the start of BetIds, a final score covers every mkt and player of an event's period
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventIds {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigArgs {
+    pub admin: [u8; 32],
+    pub market_makers: [[u8; 32]; MAX_MARKET_MAKERS],
+    pub odds_tolerance_bps: u16,
+}
+
+// borsh writes the variant index as the leading byte, so never reorder variants, only append
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum PurebetInstruction {
+    /// Token accounts can belong to the spl token or token-2022 program, the mint is needed for transfer_checked.
+    /// Orders and matches need the ids' market to be open and before its start time.
+    /// Start a new bet (maker order), the bet pda is created from ids, bettor and nonce.
+    /// 0 [w] bet, 1 [] token program, 2 [w] source, 3 [w] pool, 4 [s] authority, 5 [] bettor, 6 [ws] rent payer,
+    /// 7 [] system program, 8 [] config, 9 [] mint, 10 [] market
+    InitBet {
+        ids: BetIds,
+        stake0: u64,
+        stake1: u64,
+        side: u8,
+        to_aggregate: bool,
+        nonce: u64,
+    },
+    /// Fully match the open side of a bet. Risking stake on side to win the other stake is the worst price
+    /// the taker accepts and their stake is the most they pay, the fill is at the maker's price and only
+    /// the open stake is transferred.
+    /// 0 [w] bet, 1 [] token program, 2 [w] source, 3 [w] pool, 4 [s] bettor, 5 [] config, 6 [] mint, 7 [] market
+    Take {
+        ids: BetIds,
+        stake0: u64,
+        stake1: u64,
+        side: u8,
+    },
+    /// Match part of the open side of a bet, the matched part is moved to new_bet,
+    /// a pda created from ids, the partial taker's wallet and nonce.
+    /// The fill is at the maker's price, which can be up to max_slippage_bps worse than the price asked for
+    /// in stake0 and stake1, capped by the config's odds tolerance.
+    /// Maker stake lost to rounding is sent to the maker destination.
+    /// 0 [w] bet, 1 [] token program, 2 [w] source, 3 [w] pool, 4 [s] bettor, 5 [ws] rent payer, 6 [w] new bet,
+    /// 7 [] system program, 8 [] config, 9 [] mint, 10 [w] maker destination, 11 [] pool pda, 12 [] market
+    PartialTake {
+        ids: BetIds,
+        stake0: u64,
+        stake1: u64,
+        side: u8,
+        nonce: u64,
+        max_slippage_bps: u16,
+    },
+    /// Bettor cancels their unmatched bet.
+    /// 0 [w] bet, 1 [] token program, 2 [w] pool, 3 [w] destination, 4 [s] bettor, 5 [w] rent payer, 6 [] pool pda,
+    /// 7 [] config, 8 [] mint, 9 [] delay acc (only for to_aggregate bets)
+    Cancel { ids: BetIds, side: u8 },
+    /// Admin returns an unmatched bet, same accounts as Cancel with the admin signing instead of the bettor.
+    Refund { ids: BetIds, side: u8 },
+    /// Admin sets the cancelation delay for to_aggregate bets.
+    /// 0 [w] delay acc, 1 [s] admin, 2 [] config
+    SetDelay { seconds: u8 },
+    /// Admin pays both stakes of a matched bet to the winning side. Settle does the same without the admin
+    /// once the oracle has posted the market's result, and the admin can only grade the side that result names.
+    /// 0 [w] bet, 1 [] token program, 2 [w] pool, 3 [w] winner destination, 4 [s] admin, 5 [w] rent payer, 6 [] pool pda,
+    /// 7 [] config, 8 [] mint, 9 [] market
+    Grade { ids: BetIds, winner: u8 },
+    /// Admin voids a matched bet, each side gets their stake back. The market's posted result has to be a push or void.
+    /// 0 [w] bet, 1 [] token program, 2 [w] pool, 3 [w] destination0, 4 [w] destination1, 5 [s] admin,
+    /// 6 [w] rent payer, 7 [] pool pda, 8 [] config, 9 [] mint, 10 [] market
+    Push { ids: BetIds },
+    /// Create the config pda, only the admin key compiled into the program can do this.
+    /// 0 [w] config, 1 [ws] admin, 2 [] system program
+    InitConfig { args: ConfigArgs },
+    /// Admin replaces the admin key, market makers and odds tolerance in the config.
+    /// 0 [w] config, 1 [s] admin
+    UpdateConfig { args: ConfigArgs },
+    /// Admin whitelists the vault's mint, or moves a whitelisted mint to this vault.
+    /// The vault must be owned by the pda with seeds "pool", mint and bump.
+    /// 0 [w] config, 1 [s] admin, 2 [] vault
+    SetPool { bump: u8 },
+    /// Anyone upgrades a bet written with an older BetAcc layout to the current one, reallocating it
+    /// and topping up its rent from the payer. The top up goes to the bet's rent payer when it closes.
+    /// Headerless bets need the config to find the mint of LEGACY_POOL, and matched free bets from
+    /// before the free bet side was recorded need it to tell which side is the free bet.
+    /// 0 [w] bet, 1 [ws] payer, 2 [] system program, 3 [] config (optional)
+    MigrateBet,
+    /// Admin creates the mint's vault at the pda with seeds "vault" and mint, owned by the pool pda with
+    /// seeds "pool" and mint, and whitelists the mint with the pool pda's canonical bump.
+    /// 0 [w] config, 1 [ws] admin, 2 [w] vault, 3 [] pool pda, 4 [] mint, 5 [] token program, 6 [] system program
+    InitVault,
+    /// Admin creates the market for ids at the pda with seeds "market" and the ids, open until start_time.
+    /// 0 [w] market, 1 [ws] admin, 2 [] config, 3 [] system program
+    InitMarket { ids: BetIds, start_time: u64 },
+    /// Admin suspends, reopens or closes a market, or moves its start time. Settled markets can't change.
+    /// 0 [w] market, 1 [s] admin, 2 [] config
+    UpdateMarket {
+        ids: BetIds,
+        status: MarketStatus,
+        start_time: u64,
+    },
+    /// Anyone upgrades the config written with an older Config layout to the current one, same as MigrateBet.
+    /// 0 [w] config, 1 [ws] payer, 2 [] system program
+    MigrateConfig,
+    /// Admin sets the oracle key that posts market results.
+    /// 0 [w] config, 1 [s] admin
+    SetOracle { oracle: [u8; 32] },
+    /// Oracle posts the result of a market, settling it. Results are final and stop any new orders or matches.
+    /// Markets GradeMarket can grade from the score can only be voided, half wins only go on asian spreads and totals.
+    /// 0 [w] market, 1 [s] oracle, 2 [] config
+    PostResult { ids: BetIds, result: MarketResult },
+    /// Anyone pays out a matched bet from its market's posted result and closes it. The winner's destination
+    /// gets both stakes and the loser's isn't read, on a push or void each side gets their stake back.
+    /// On a half win the winner gets their stake and half the loser's, the loser gets the rest back.
+    /// 0 [w] bet, 1 [] token program, 2 [w] pool, 3 [w] destination0, 4 [w] destination1, 5 [w] rent payer,
+    /// 6 [] pool pda, 7 [] config, 8 [] mint, 9 [] market
+    Settle { ids: BetIds },
+    /// Oracle posts the final score of an event's period at the pda with seeds "score" and the event ids.
+    /// Scores are final, every moneyline, spread and total market on the event is graded from it.
+    /// 0 [w] score, 1 [ws] oracle, 2 [] config, 3 [] system program
+    PostScore { event: EventIds, home: u32, away: u32 },
+    /// Anyone settles a market from its event's posted score, the result is decoded from the market's mkt.
+    /// 0 [w] market, 1 [] score
+    GradeMarket { ids: BetIds },
+}
+
+impl PurebetInstruction {
+    // the only way instruction data is read: borsh rejects short data, trailing bytes and bools
+    // that aren't 0 or 1, then every field with a restricted range is checked before any handler runs
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let instruction = Self::try_from_slice(data).map_err(|_| {
+            msg!("instruction data doesn't decode");
+            PurebetError::InvalidInstruction
+        })?;
+        instruction.validate()?;
+        Ok(instruction)
+    }
+
+    fn validate(&self) -> Result<(), PurebetError> {
+        let side = match self {
+            PurebetInstruction::InitBet { side, .. }
+            | PurebetInstruction::Take { side, .. }
+            | PurebetInstruction::PartialTake { side, .. }
+            | PurebetInstruction::Cancel { side, .. }
+            | PurebetInstruction::Refund { side, .. } => Some(*side),
+            PurebetInstruction::Grade { winner, .. } => Some(*winner),
+            _ => None,
+        };
+        if side.is_some_and(|side| side > 1) {
+            msg!("side must be 0 or 1");
+            return Err(PurebetError::InvalidSide);
+        }
+        match self {
+            PurebetInstruction::PartialTake { max_slippage_bps, .. } if *max_slippage_bps > MAX_BPS => {
+                msg!("slippage can't be more than 10000 bps");
+                Err(PurebetError::InvalidOddsTolerance)
+            }
+            PurebetInstruction::InitConfig { args } | PurebetInstruction::UpdateConfig { args }
+                if args.odds_tolerance_bps > MAX_BPS =>
+            {
+                msg!("odds tolerance can't be more than 10000 bps");
+                Err(PurebetError::InvalidOddsTolerance)
+            }
+            PurebetInstruction::PostResult { result, .. } if *result == MarketResult::Pending => {
+                msg!("pending isn't a result");
+                Err(PurebetError::InvalidMarketStatus)
+            }
+            _ => Ok(()),
+        }
+    }
+}
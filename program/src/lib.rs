@@ -1,21 +1,35 @@
-use borsh::BorshDeserialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
-    entrypoint,
     entrypoint::ProgramResult,
     msg,
-    program_error::ProgramError,
     pubkey::Pubkey,
 };
+pub mod accounts;
 pub mod cancel;
+pub mod config;
+#[cfg(feature = "no-entrypoint")]
+pub mod client;
+pub mod error;
+pub mod instruction;
 pub mod maker;
+pub mod market;
+pub mod math;
+pub mod migrate;
+pub mod odds;
 pub mod partial_taker;
+pub mod score;
+pub mod settle;
 pub mod taker;
 pub mod token;
 pub mod utils;
 
+use error::PurebetError;
+use instruction::PurebetInstruction;
+use utils::ProgramAccount;
+
 // Declare and export the program's entrypoint
-entrypoint!(process_instruction);
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
 // Program entrypoint's implementation
 pub fn process_instruction(
@@ -23,61 +37,100 @@ pub fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
+    let instruction = PurebetInstruction::unpack(instruction_data)?;
+
+    // new bets are created by the program, so there is no existing account to load
+    if let PurebetInstruction::InitBet {
+        ids,
+        stake0,
+        stake1,
+        side,
+        to_aggregate,
+        nonce,
+    } = instruction
+    {
+        msg!("Thank you for betting with Purebet!");
+        return maker::bet(accounts, program_id, ids, stake0, stake1, side, to_aggregate, nonce);
+    }
+    match instruction {
+        PurebetInstruction::InitConfig { args } => return config::init(accounts, program_id, args),
+        PurebetInstruction::UpdateConfig { args } => return config::update(accounts, program_id, args),
+        PurebetInstruction::SetPool { bump } => return config::set_pool(accounts, program_id, bump),
+        PurebetInstruction::InitVault => return config::init_vault(accounts, program_id),
+        PurebetInstruction::InitMarket { ids, start_time } => return market::init(accounts, program_id, ids, start_time),
+        PurebetInstruction::UpdateMarket { ids, status, start_time } => {
+            return market::update(accounts, program_id, ids, status, start_time)
+        }
+        PurebetInstruction::SetOracle { oracle } => return config::set_oracle(accounts, program_id, oracle),
+        PurebetInstruction::PostResult { ids, result } => return market::post_result(accounts, program_id, ids, result),
+        PurebetInstruction::PostScore { event, home, away } => {
+            return market::post_score(accounts, program_id, event, home, away)
+        }
+        PurebetInstruction::GradeMarket { ids } => return market::grade(accounts, program_id, ids),
+        // older layouts don't load as a BetAcc, so migration reads the account itself
+        PurebetInstruction::MigrateBet => return migrate::bet(accounts, program_id),
+        PurebetInstruction::MigrateConfig => return migrate::config(accounts, program_id),
+        _ => {}
+    }
+
     // Iterating accounts is safer than indexing
     let accounts_iter = &mut accounts.iter();
 
-    // call it bet even though it could be the cancelation delay account
+    // call it bet even though it could be the cancelation delay account, the account type
+    // byte makes sure it is the one the instruction expects
     let bet = next_account_info(accounts_iter)?;
     // The account must be owned by the program in order to modify its data
     if bet.owner != program_id {
         msg!("bet doesn't belong to this program id");
-        return Err(ProgramError::IncorrectProgramId);
+        return Err(PurebetError::IncorrectOwner.into());
     }
-    if accounts.len() == 2 {
-        let delay_acc = utils::CancelDelay::try_from_slice(&bet.data.borrow())?;
-        return cancel::set_delay(delay_acc, accounts, instruction_data);
+    if let PurebetInstruction::SetDelay { seconds } = instruction {
+        let delay_acc = utils::CancelDelay::unpack_or_new(&bet.data.borrow())?;
+        return cancel::set_delay(delay_acc, accounts, program_id, seconds);
     }
 
-    let bet_account = utils::BetAcc::try_from_slice(&bet.data.borrow())?;
+    let bet_account = utils::BetAcc::unpack(&bet.data.borrow())?;
     let wallet0_is_blank = utils::blank_wallet(bet_account.wallet0);
     let wallet1_is_blank = utils::blank_wallet(bet_account.wallet1);
-    let mut result = Ok(());
+    let is_open = wallet0_is_blank && wallet1_is_blank;
+    let is_matched = !wallet0_is_blank && !wallet1_is_blank;
 
-    if wallet0_is_blank && wallet1_is_blank {
-        msg!("Thank you for betting with Purebet!");
-        //start bet
-        result = maker::bet(bet_account, accounts, instruction_data);
-    } else if wallet0_is_blank || wallet1_is_blank {
-        if instruction_data.len() == 37 {
-            // match bet or partial match (differentiate by num of accs)
+    // every instruction only applies to bets in a certain state
+    match instruction {
+        PurebetInstruction::Take {
+            ids,
+            stake0,
+            stake1,
+            side,
+        } if !is_open && !is_matched => {
+            msg!("Thank you for betting with Purebet");
+            taker::bet(bet_account, accounts, program_id, ids, stake0, stake1, side)
+        }
+        PurebetInstruction::PartialTake {
+            ids,
+            stake0,
+            stake1,
+            side,
+            nonce,
+            max_slippage_bps,
+        } if !is_open && !is_matched => {
             msg!("Thank you for betting with Purebet");
-            if accounts.len() == 5 {
-                result = taker::bet(bet_account, accounts, instruction_data);
-            } else if accounts.len() == 7 {
-                msg!("got to call to partial taker without issues");
-                result = partial_taker::bet(bet_account, accounts, instruction_data, program_id);
-            }
-        } else {
-            if accounts.len() == 7 || accounts[7].data_len() == 2 {
-                let is_refund: bool = instruction_data[21] as bool;
-                result = cancel::bet(
-                    bet_account,
-                    accounts,
-                    instruction_data,
-                    program_id,
-                    is_refund,
-                );
-            } else {
-                result = Err(ProgramError::InvalidArgument);
-            }
+            partial_taker::bet(bet_account, accounts, program_id, ids, stake0, stake1, side, nonce, max_slippage_bps)
+        }
+        PurebetInstruction::Cancel { ids, side } if !is_open && !is_matched => {
+            cancel::bet(bet_account, accounts, program_id, ids, side, false)
+        }
+        PurebetInstruction::Refund { ids, side } if !is_open && !is_matched => {
+            cancel::bet(bet_account, accounts, program_id, ids, side, true)
+        }
+        PurebetInstruction::Grade { ids, winner } if is_matched => {
+            settle::grade(bet_account, accounts, program_id, ids, winner)
+        }
+        PurebetInstruction::Push { ids } if is_matched => settle::push(bet_account, accounts, program_id, ids),
+        PurebetInstruction::Settle { ids } if is_matched => settle::bet(bet_account, accounts, program_id, ids),
+        _ => {
+            msg!("instruction doesn't apply to the current state of the bet");
+            Err(PurebetError::InvalidBetState.into())
         }
-    // or cancel or refund (differentiate by num of accs)
-    //need to remember to check bettor assoc tok belongs to bettor
-    } else {
-        //grade or push (differentiate by number of accs)
-        //need to remember to check bettor assoc tok belongs to bettor
-        result = Ok(());
     }
-
-    result
 }
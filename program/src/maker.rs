@@ -1,64 +1,84 @@
-use borsh::BorshSerialize;
+use crate::utils::ProgramAccount;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::AccountInfo,
     clock::Clock,
     entrypoint::ProgramResult,
     msg,
-    program_error::ProgramError,
+    pubkey::Pubkey,
     sysvar::Sysvar
 };
+use crate::accounts::MakerAccounts;
+use crate::error::PurebetError;
+use crate::instruction::BetIds;
+use crate::market;
+use crate::math;
 use crate::utils;
 use crate::token;
 
+#[allow(clippy::too_many_arguments)]
 pub fn bet(
-    mut bet_account: utils::BetAcc,
     accounts: &[AccountInfo],
-    instruction_data: &[u8],
+    program_id: &Pubkey,
+    ids: BetIds,
+    stake0: u64,
+    stake1: u64,
+    side: u8,
+    to_aggregate: bool,
+    nonce: u64,
 ) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-
-    let bet = next_account_info(accounts_iter)?;
-    let _tok_prog = next_account_info(accounts_iter)?;
-    let _source = next_account_info(accounts_iter)?;
-    let destination = next_account_info(accounts_iter)?;
-    let authority = next_account_info(accounts_iter)?; // not necessarily the bettor if using free bet
-    let bettor = next_account_info(accounts_iter)?;
-    let rent_payer = next_account_info(accounts_iter)?;
-
-    if !utils::correct_pool((*destination).key.to_bytes()) {
-        msg!("Incorrect pool");
-        return Err(ProgramError::InvalidArgument);
-    }
-    if !utils::blank_acc(&bet_account){
-        msg!("trying to start bet in non empty bet account");
-        return Err(ProgramError::AccountAlreadyInitialized);
+    let accounts = MakerAccounts::parse(accounts)?;
+    // a zero stake on either side leaves nothing to match or nothing to win
+    if stake0 == 0 || stake1 == 0 {
+        msg!("both stakes have to be more than zero");
+        return Err(PurebetError::ZeroStake.into());
     }
+    // a bet on a code that doesn't decode could never be graded
+    market::check_codes(&ids)?;
+    let config_acc = utils::load_config(accounts.config, program_id)?;
+    market::check_open(accounts.market, program_id, &ids)?;
+    // the pool passed in decides which mint the bet is in
+    let pool = match config_acc.pool_for_vault(accounts.pool.key.to_bytes()) {
+        Some(pool) => pool,
+        None => {
+            msg!("Incorrect pool");
+            return Err(PurebetError::IncorrectPool.into());
+        }
+    };
+    accounts.check_mint(pool.mint)?;
+    utils::create_bet_account(accounts.bet, accounts.rent_payer, accounts.system_program, program_id, &ids, accounts.bettor.key, nonce)?;
+
     // set account values
-    utils::set_bet_ids(&mut bet_account, instruction_data);
-    bet_account.stake0 = utils::bytes_to_num(&instruction_data, 20, 28);
-    bet_account.stake1 = utils::bytes_to_num(&instruction_data, 28, 36);
+    let mut bet_account = utils::BetAcc::default();
+    utils::set_bet_ids(&mut bet_account, &ids);
+    bet_account.stake0 = stake0;
+    bet_account.stake1 = stake1;
 
-    let side = instruction_data[36];
     let stake: u64;
     if side == 0 {
-        bet_account.wallet0 = (*bettor).key.to_bytes();
+        bet_account.wallet0 = accounts.bettor.key.to_bytes();
         stake = bet_account.stake0;
     } else {
-        bet_account.wallet1 = (*bettor).key.to_bytes();
+        bet_account.wallet1 = accounts.bettor.key.to_bytes();
         stake = bet_account.stake1;
     }
-    bet_account.rent_payer = (*rent_payer).key.to_bytes();
-    bet_account.is_free_bet = !utils::equal_wallets((*authority).key.to_bytes(), (*bettor).key.to_bytes());
-    bet_account.to_aggregate = instruction_data[37] == 1;
+    bet_account.rent_payer = accounts.rent_payer.key.to_bytes();
+    bet_account.is_free_bet = !utils::equal_wallets(accounts.authority.key.to_bytes(), accounts.bettor.key.to_bytes());
+    // remember whose stake the free bet is, settlement can't tell from the config later
+    if bet_account.is_free_bet {
+        bet_account.free_bet_side = side;
+        bet_account.free_bet_funder = bet_account.rent_payer;
+    }
+    bet_account.to_aggregate = to_aggregate;
+    bet_account.mint = pool.mint;
 
     let clock = Clock::get()?;
-    bet_account.placed_at = clock.unix_timestamp as u64;
+    bet_account.placed_at = math::timestamp(&clock)?;
     // call another function to send the correct tokens to the correct address
     // need to check return value of this for error and not run the below line if sending tokens errors
-    let result = token::send(accounts, 2, 3, 4, 1, stake);
+    let result = token::send(accounts.source, accounts.pool, accounts.authority, accounts.token_program, accounts.mint, stake);
     match result {
         Ok(_result) => {
-            bet_account.serialize(&mut *bet.data.borrow_mut())?;
+            bet_account.pack(&mut accounts.bet.data.borrow_mut())?;
         }
         Err(err) => {
             return Err(err);
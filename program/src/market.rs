@@ -0,0 +1,342 @@
+// markets describe what a set of ids is betting on, bets can only be placed and matched while theirs is open
+use crate::accounts::{GradeMarketAccounts, InitMarketAccounts, PostResultAccounts, PostScoreAccounts, UpdateMarketAccounts};
+use crate::error::PurebetError;
+use crate::instruction::{BetIds, EventIds};
+use crate::math;
+use crate::score;
+use crate::utils::{self, MarketResult, MarketStatus, ProgramAccount};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+    system_program,
+    sysvar::Sysvar,
+};
+
+// what a market's mkt code bets on, side 0 is always home, the outcome happening or over.
+// asian lines are quarter lines (x.25, x.75), graded as half the stake on each neighbouring half line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketKind {
+    Moneyline,         // 0, home against away, a draw pushes
+    Home,              // 1, home wins
+    Away,              // 2, away wins
+    Draw,              // 3
+    Spread(i16),       // 200 + half points added to home's score, 4 to 396
+    AsianSpread(i16),  // odd quarter points added to home's score, 400 to 599 for -49.75 to +49.75
+    AsianTotal(u16),   // odd quarter points of both scores together, 600 to 999 for 0.25 to 199.75
+    Total(u16),        // 1000 + half points of both scores together
+}
+
+const SPREAD_BASE: u16 = 200;
+const MAX_SPREAD: i16 = 196; // 98 points either way keeps spreads clear of the fixed codes
+const ASIAN_SPREAD_BASE: u16 = 500; // +0.25, -0.25 is one below
+const ASIAN_SPREADS: std::ops::RangeInclusive<u16> = 400..=599;
+const ASIAN_TOTAL_BASE: u16 = 600; // 0.25
+const TOTAL_BASE: u16 = 1000;
+
+impl MarketKind {
+    pub fn encode(self) -> Option<u16> {
+        let code = match self {
+            MarketKind::Moneyline => 0,
+            MarketKind::Home => 1,
+            MarketKind::Away => 2,
+            MarketKind::Draw => 3,
+            MarketKind::Spread(half_points) if half_points.abs() <= MAX_SPREAD => {
+                SPREAD_BASE.wrapping_add_signed(half_points)
+            }
+            MarketKind::Spread(_) => return None,
+            // only odd quarter points, even ones are half point lines, two codes per point
+            MarketKind::AsianSpread(quarters) if quarters % 2 != 0 => {
+                ASIAN_SPREAD_BASE.checked_add_signed((quarters - 1).div_euclid(2))?
+            }
+            MarketKind::AsianTotal(quarters) if quarters % 2 != 0 => ASIAN_TOTAL_BASE.checked_add(quarters / 2)?,
+            MarketKind::AsianSpread(_) | MarketKind::AsianTotal(_) => return None,
+            MarketKind::Total(half_points) => TOTAL_BASE.checked_add(half_points)?,
+        };
+        // asian lines past their range would run into the next kind's codes
+        match self {
+            MarketKind::AsianSpread(_) if !ASIAN_SPREADS.contains(&code) => None,
+            MarketKind::AsianTotal(_) if code >= TOTAL_BASE => None,
+            _ => Some(code),
+        }
+    }
+
+    pub fn decode(mkt: u16) -> Option<Self> {
+        match mkt {
+            0 => Some(MarketKind::Moneyline),
+            1 => Some(MarketKind::Home),
+            2 => Some(MarketKind::Away),
+            3 => Some(MarketKind::Draw),
+            _ if mkt >= TOTAL_BASE => Some(MarketKind::Total(mkt - TOTAL_BASE)),
+            _ if mkt >= ASIAN_TOTAL_BASE => Some(MarketKind::AsianTotal((mkt - ASIAN_TOTAL_BASE) * 2 + 1)),
+            _ if ASIAN_SPREADS.contains(&mkt) => {
+                Some(MarketKind::AsianSpread((mkt as i16 - ASIAN_SPREAD_BASE as i16) * 2 + 1))
+            }
+            _ => {
+                let half_points = mkt as i16 - SPREAD_BASE as i16;
+                (half_points.abs() <= MAX_SPREAD).then_some(MarketKind::Spread(half_points))
+            }
+        }
+    }
+}
+
+// player code: a byte each for the first initial, last initial and the 2nd and 3rd letters of the
+// last name, from the low byte up, as uppercase ascii and 0 after the end of short last names.
+// the number of spaces in the last name (ex de xxx) goes in the top bit of each byte, the low
+// byte's is the lowest bit. only ascii letters count, spaces and anything else like the
+// apostrophe in O'Neal are skipped. 0 is a team market with no player
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerCode {
+    pub first_initial: u8,
+    pub last_name: [u8; 3], // 0 after the last letter of short last names
+    pub spaces: u8,
+}
+
+const LETTER_MASK: u8 = 0x7f;
+const SPACE_BIT: u8 = 0x80;
+const MAX_SPACES: u8 = 15; // one bit in each of the 4 bytes
+
+impl PlayerCode {
+    // letters are ascii, either case
+    pub fn new(first_name: &str, last_name: &str) -> Option<Self> {
+        let letters = |name: &str| name.bytes().filter(u8::is_ascii_alphabetic).map(|b| b.to_ascii_uppercase()).collect::<Vec<u8>>();
+        let last_letters = letters(last_name);
+        let mut last = [0; 3];
+        for (letter, &b) in last.iter_mut().zip(&last_letters) {
+            *letter = b;
+        }
+        let spaces = last_name.trim().bytes().filter(|b| *b == b' ').count();
+        let code = PlayerCode {
+            first_initial: *letters(first_name).first()?,
+            last_name: last,
+            spaces: u8::try_from(spaces).ok()?,
+        };
+        code.encode().map(|_| code)
+    }
+
+    pub fn encode(&self) -> Option<u32> {
+        if self.spaces > MAX_SPACES || self.first_initial == 0 || self.last_name[0] == 0 {
+            return None;
+        }
+        // a letter can't follow the end of the last name
+        if self.last_name[1] == 0 && self.last_name[2] != 0 {
+            return None;
+        }
+        let letters = [self.first_initial, self.last_name[0], self.last_name[1], self.last_name[2]];
+        let mut bytes = [0; 4];
+        for (i, letter) in letters.into_iter().enumerate() {
+            if letter != 0 && !letter.is_ascii_uppercase() {
+                return None;
+            }
+            let space = if self.spaces >> i & 1 == 1 { SPACE_BIT } else { 0 };
+            bytes[i] = letter | space;
+        }
+        Some(u32::from_le_bytes(bytes))
+    }
+
+    pub fn decode(code: u32) -> Option<Self> {
+        let bytes = code.to_le_bytes();
+        let mut spaces = 0;
+        for (i, byte) in bytes.iter().enumerate() {
+            if byte & SPACE_BIT != 0 {
+                spaces |= 1 << i;
+            }
+        }
+        let letter = |i: usize| bytes[i] & LETTER_MASK;
+        let player = PlayerCode {
+            first_initial: letter(0),
+            last_name: [letter(1), letter(2), letter(3)],
+            spaces,
+        };
+        // only codes encode would produce, so every player has exactly one code
+        (player.encode() == Some(code)).then_some(player)
+    }
+}
+
+// bets can only be placed on ids whose mkt and player mean something
+pub fn check_codes(ids: &BetIds) -> ProgramResult {
+    if MarketKind::decode(ids.mkt).is_none() {
+        msg!("mkt {} isn't a market code", ids.mkt);
+        return Err(PurebetError::InvalidMarketCode.into());
+    }
+    if ids.player != 0 && PlayerCode::decode(ids.player).is_none() {
+        msg!("player {} isn't a player code", ids.player);
+        return Err(PurebetError::InvalidMarketCode.into());
+    }
+    Ok(())
+}
+
+pub fn init(accounts: &[AccountInfo], program_id: &Pubkey, ids: BetIds, start_time: u64) -> ProgramResult {
+    let InitMarketAccounts {
+        market,
+        admin,
+        config,
+        system_program,
+    } = InitMarketAccounts::parse(accounts)?;
+
+    let config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_admin(admin.key.to_bytes()) {
+        msg!("only the admin key can create markets");
+        return Err(PurebetError::NotAdmin.into());
+    }
+    let market_seeds = utils::MarketSeeds::new(&ids);
+    let seeds = market_seeds.seeds();
+    let (address, bump) = Pubkey::find_program_address(&seeds, program_id);
+    if address != *market.key {
+        msg!("market account isn't derived from its ids");
+        return Err(PurebetError::IncorrectMarket.into());
+    }
+    if market.owner != &system_program::id() || !market.data_is_empty() {
+        msg!("market already exists");
+        return Err(PurebetError::MarketAlreadyInitialized.into());
+    }
+    let bump = [bump];
+    let signer_seeds = [&seeds[..], &[&bump[..]]].concat();
+    utils::create_pda_account(market, admin, system_program, program_id, utils::MARKET_LEN, &signer_seeds)?;
+
+    let market_acc = utils::Market {
+        sport: ids.sport,
+        league: ids.league,
+        event: ids.event,
+        period: ids.period,
+        mkt: ids.mkt,
+        player: ids.player,
+        start_time,
+        ..Default::default()
+    };
+    market_acc.pack(&mut market.data.borrow_mut())?;
+    Ok(())
+}
+
+// suspend, reopen or close a market, or move its start time
+pub fn update(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    ids: BetIds,
+    status: MarketStatus,
+    start_time: u64,
+) -> ProgramResult {
+    let UpdateMarketAccounts { market, admin, config } = UpdateMarketAccounts::parse(accounts)?;
+
+    let config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_admin(admin.key.to_bytes()) {
+        msg!("only the admin key can update markets");
+        return Err(PurebetError::NotAdmin.into());
+    }
+    let mut market_acc = utils::load_market(market, program_id, &ids)?;
+    if market_acc.status == MarketStatus::Settled || status == MarketStatus::Settled {
+        msg!("markets are only settled by posting their result, and stay settled");
+        return Err(PurebetError::InvalidMarketStatus.into());
+    }
+    market_acc.status = status;
+    market_acc.start_time = start_time;
+    market_acc.pack(&mut market.data.borrow_mut())?;
+    Ok(())
+}
+
+// one result per market, every bet on it is then settled against it
+pub fn post_result(accounts: &[AccountInfo], program_id: &Pubkey, ids: BetIds, result: MarketResult) -> ProgramResult {
+    let PostResultAccounts { market, oracle, config } = PostResultAccounts::parse(accounts)?;
+
+    let config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_oracle(oracle.key.to_bytes()) {
+        msg!("only the oracle key can post results");
+        return Err(PurebetError::NotOracle.into());
+    }
+    let mut market_acc = utils::load_market(market, program_id, &ids)?;
+    // bets may already be paid out against the posted result
+    if market_acc.status == MarketStatus::Settled {
+        msg!("market result is already posted");
+        return Err(PurebetError::InvalidMarketStatus.into());
+    }
+    // lines the score grades take their result from it, so bets on them can't be paid another way
+    if score::is_gradable(ids.mkt, ids.player) && result != MarketResult::Void {
+        msg!("markets graded from the score can only be voided");
+        return Err(PurebetError::InvalidMarketResult.into());
+    }
+    // only quarter lines are split over two half lines, anything else wins, loses or pushes whole
+    let half = matches!(result, MarketResult::Side0HalfWon | MarketResult::Side1HalfWon);
+    let quarter_line = matches!(MarketKind::decode(ids.mkt), Some(MarketKind::AsianSpread(_) | MarketKind::AsianTotal(_)));
+    if half && !quarter_line {
+        msg!("half wins are only posted for asian spreads and totals");
+        return Err(PurebetError::InvalidMarketResult.into());
+    }
+    market_acc.status = MarketStatus::Settled;
+    market_acc.result = result;
+    market_acc.pack(&mut market.data.borrow_mut())?;
+    Ok(())
+}
+
+// one score per event period, so every line on it is graded from the same numbers
+pub fn post_score(accounts: &[AccountInfo], program_id: &Pubkey, event: EventIds, home: u32, away: u32) -> ProgramResult {
+    let PostScoreAccounts {
+        score,
+        oracle,
+        config,
+        system_program,
+    } = PostScoreAccounts::parse(accounts)?;
+
+    let config_acc = utils::load_config(config, program_id)?;
+    if !config_acc.is_oracle(oracle.key.to_bytes()) {
+        msg!("only the oracle key can post scores");
+        return Err(PurebetError::NotOracle.into());
+    }
+    let score_seeds = utils::ScoreSeeds::new(&event);
+    let seeds = score_seeds.seeds();
+    let (address, bump) = Pubkey::find_program_address(&seeds, program_id);
+    if address != *score.key {
+        msg!("score account isn't derived from its event");
+        return Err(PurebetError::IncorrectScore.into());
+    }
+    if score.owner != &system_program::id() || !score.data_is_empty() {
+        msg!("score already posted");
+        return Err(PurebetError::ScoreAlreadyPosted.into());
+    }
+    let bump = [bump];
+    let signer_seeds = [&seeds[..], &[&bump[..]]].concat();
+    utils::create_pda_account(score, oracle, system_program, program_id, utils::SCORE_LEN, &signer_seeds)?;
+
+    let score_acc = utils::Score {
+        sport: event.sport,
+        league: event.league,
+        event: event.event,
+        period: event.period,
+        home,
+        away,
+    };
+    score_acc.pack(&mut score.data.borrow_mut())?;
+    Ok(())
+}
+
+// settle a market from its event's score, same as the oracle posting the result decoded from mkt
+pub fn grade(accounts: &[AccountInfo], program_id: &Pubkey, ids: BetIds) -> ProgramResult {
+    let GradeMarketAccounts { market, score } = GradeMarketAccounts::parse(accounts)?;
+
+    let mut market_acc = utils::load_market(market, program_id, &ids)?;
+    if market_acc.status == MarketStatus::Settled {
+        msg!("market result is already posted");
+        return Err(PurebetError::InvalidMarketStatus.into());
+    }
+    let score_acc = utils::load_score(score, program_id, &ids.event_ids())?;
+    market_acc.result = score::market_result(ids.mkt, ids.player, score_acc.home, score_acc.away)?;
+    market_acc.status = MarketStatus::Settled;
+    market_acc.pack(&mut market.data.borrow_mut())?;
+    Ok(())
+}
+
+// new orders and matches need the bet's market to be open and not started yet
+pub fn check_open(market: &AccountInfo, program_id: &Pubkey, ids: &BetIds) -> ProgramResult {
+    let market_acc = utils::load_market(market, program_id, ids)?;
+    if market_acc.status != MarketStatus::Open {
+        msg!("market is {:?}", market_acc.status);
+        return Err(PurebetError::MarketNotOpen.into());
+    }
+    let clock = Clock::get()?;
+    if math::timestamp(&clock)? >= market_acc.start_time {
+        msg!("market has already started");
+        return Err(PurebetError::MarketStarted.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+// checked stake, lamport and time math, anything that would wrap fails with ArithmeticOverflow
+use crate::error::PurebetError;
+use solana_program::{account_info::AccountInfo, clock::Clock, msg, program_error::ProgramError};
+
+pub fn add(a: u64, b: u64) -> Result<u64, ProgramError> {
+    a.checked_add(b).ok_or_else(overflow)
+}
+
+// unix timestamp as stored in placed_at, a clock from before 1970 is treated as an overflow
+pub fn timestamp(clock: &Clock) -> Result<u64, ProgramError> {
+    u64::try_from(clock.unix_timestamp).map_err(|_| overflow())
+}
+
+// move all lamports out of from into to, used when closing accounts
+pub fn drain_lamports(from: &AccountInfo, to: &AccountInfo) -> Result<(), ProgramError> {
+    let total_lamports = from.lamports();
+    let to_lamports = add(to.lamports(), total_lamports)?;
+    **from.try_borrow_mut_lamports()? = 0;
+    **to.try_borrow_mut_lamports()? = to_lamports;
+    Ok(())
+}
+
+fn overflow() -> ProgramError {
+    msg!("arithmetic overflow");
+    PurebetError::ArithmeticOverflow.into()
+}
@@ -0,0 +1,172 @@
+// upgrades accounts written with an older layout, so live orders and the config survive new fields
+use crate::accounts::MigrateAccounts;
+use crate::error::PurebetError;
+use crate::utils::{self, ProgramAccount};
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+pub fn bet(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let MigrateAccounts {
+        account: bet,
+        payer,
+        system_program,
+        config,
+    } = MigrateAccounts::parse(accounts)?;
+
+    if bet.owner == program_id && bet.data_len() == utils::BET_ACC_V0_LEN {
+        let bet_account = legacy_bet(bet, config, program_id)?;
+        let free_bet_side = free_bet_side(&bet_account, config, program_id)?;
+        resize(bet, payer, system_program, utils::BET_ACC_LEN)?;
+        bet_account.upgrade(free_bet_side).pack(&mut bet.data.borrow_mut())?;
+        return Ok(());
+    }
+
+    let version = version_of(bet, program_id, utils::AccountType::Bet)?;
+    // anything else the program can't read, so there is nothing to upgrade from
+    let bet_account: utils::BetAccV2 = match version {
+        utils::BetAccV1::VERSION => utils::BetAccV1::unpack(&bet.data.borrow())?.into(),
+        utils::BetAccV2::VERSION => utils::BetAccV2::unpack(&bet.data.borrow())?,
+        utils::BetAcc::VERSION => {
+            msg!("bet is already on the current layout");
+            return Ok(());
+        }
+        _ => {
+            msg!("unsupported bet account version {}", version);
+            return Err(PurebetError::WrongAccountVersion.into());
+        }
+    };
+
+    let free_bet_side = free_bet_side(&bet_account, config, program_id)?;
+    let bet_account = bet_account.upgrade(free_bet_side);
+
+    resize(bet, payer, system_program, utils::BET_ACC_LEN)?;
+    bet_account.pack(&mut bet.data.borrow_mut())?;
+    Ok(())
+}
+
+pub fn config(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let MigrateAccounts {
+        account: config,
+        payer,
+        system_program,
+        ..
+    } = MigrateAccounts::parse(accounts)?;
+
+    if *config.key != utils::find_config_address(program_id).0 {
+        msg!("incorrect config account");
+        return Err(PurebetError::IncorrectConfig.into());
+    }
+    let version = version_of(config, program_id, utils::AccountType::Config)?;
+    let config_acc: utils::Config = match version {
+        utils::ConfigV1::VERSION => utils::ConfigV1::unpack(&config.data.borrow())?.into(),
+        utils::Config::VERSION => {
+            msg!("config is already on the current layout");
+            return Ok(());
+        }
+        _ => {
+            msg!("unsupported config account version {}", version);
+            return Err(PurebetError::WrongAccountVersion.into());
+        }
+    };
+
+    resize(config, payer, system_program, utils::CONFIG_LEN)?;
+    config_acc.pack(&mut config.data.borrow_mut())?;
+    Ok(())
+}
+
+// headerless bet from before accounts were typed, its stakes sit in the config's pool for LEGACY_POOL
+fn legacy_bet(bet: &AccountInfo, config: Option<&AccountInfo>, program_id: &Pubkey) -> Result<utils::BetAccV2, ProgramError> {
+    let bet_account = utils::BetAccV0::try_from_slice(&bet.data.borrow())?;
+    if utils::blank_wallet(bet_account.wallet0) && utils::blank_wallet(bet_account.wallet1) {
+        msg!("headerless account doesn't hold a bet");
+        return Err(PurebetError::InvalidBetState.into());
+    }
+    let config = match config {
+        Some(config) => config,
+        None => {
+            msg!("headerless bets need the config account to find their mint");
+            return Err(PurebetError::IncorrectConfig.into());
+        }
+    };
+    let config_acc = utils::load_config(config, program_id)?;
+    let mint = match config_acc.pool_for_vault(utils::LEGACY_POOL) {
+        Some(pool) => pool.mint,
+        None => {
+            msg!("the legacy pool isn't in the config");
+            return Err(PurebetError::IncorrectPool.into());
+        }
+    };
+    Ok(bet_account.upgrade(mint).into())
+}
+
+// older bets didn't record the free bet side, an open bet only has the maker's side and
+// a matched one was matched by a market maker, so the side that isn't a market maker is free
+fn free_bet_side(bet_account: &utils::BetAccV2, config: Option<&AccountInfo>, program_id: &Pubkey) -> Result<u8, ProgramError> {
+    if !bet_account.is_free_bet {
+        return Ok(0);
+    }
+    if utils::blank_wallet(bet_account.wallet0) || utils::blank_wallet(bet_account.wallet1) {
+        return Ok(u8::from(utils::blank_wallet(bet_account.wallet0)));
+    }
+    let config = match config {
+        Some(config) => config,
+        None => {
+            msg!("matched free bets need the config account to find the free bet side");
+            return Err(PurebetError::IncorrectConfig.into());
+        }
+    };
+    let config_acc = utils::load_config(config, program_id)?;
+    match (
+        config_acc.is_market_maker(bet_account.wallet0),
+        config_acc.is_market_maker(bet_account.wallet1),
+    ) {
+        (true, false) => Ok(1),
+        (false, true) => Ok(0),
+        _ => {
+            msg!("can't tell which side of the free bet isn't the market maker");
+            Err(PurebetError::InvalidBetState.into())
+        }
+    }
+}
+
+// layout version of a program account of the given type
+fn version_of(account: &AccountInfo, program_id: &Pubkey, account_type: utils::AccountType) -> Result<u8, PurebetError> {
+    if account.owner != program_id {
+        msg!("account doesn't belong to this program id");
+        return Err(PurebetError::IncorrectOwner);
+    }
+    let data = account.data.borrow();
+    if data.len() < utils::HEADER_LEN || data[0] != account_type as u8 {
+        msg!("account isn't a {:?} account", account_type);
+        return Err(PurebetError::WrongAccountType);
+    }
+    Ok(data[1])
+}
+
+// grow the account to the new layout's size, topping up its rent from the payer
+fn resize<'a>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    len: usize,
+) -> ProgramResult {
+    let rent = Rent::get()?.minimum_balance(len);
+    if account.lamports() < rent {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, rent - account.lamports()),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    account.realloc(len, true)?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+// odds are never stored, a bet's price is the ratio between the maker's stake and the open stake.
+// everything here cross multiplies in u128 so the ratio is compared exactly, and whenever a
+// stake has to be rounded it is rounded so the maker never backs more than their price
+
+// stakes of side and of the other side
+pub fn sides(stake0: u64, stake1: u64, side: u8) -> (u64, u64) {
+    if side == 0 {
+        (stake0, stake1)
+    } else {
+        (stake1, stake0)
+    }
+}
+
+// inverse of sides, puts stake on side and other on the other side
+pub fn by_side(stake: u64, other: u64, side: u8) -> (u64, u64) {
+    if side == 0 {
+        (stake, other)
+    } else {
+        (other, stake)
+    }
+}
+
+pub const MAX_BPS: u16 = 10_000;
+
+// true if risking stake to win counter is no better for the taker than the maker's offer
+// of risking open to win maker
+pub fn within_price(stake: u64, counter: u64, open: u64, maker: u64) -> bool {
+    within_slippage(stake, counter, open, maker, 0)
+}
+
+// true if the maker's offer is at most bps worse for the taker than risking stake to win counter,
+// i.e. maker / open >= counter / stake * (1 - bps / 10000)
+pub fn within_slippage(stake: u64, counter: u64, open: u64, maker: u64, bps: u16) -> bool {
+    let offered = (stake as u128) * (maker as u128);
+    let asked = (counter as u128) * (open as u128);
+    let bps = bps.min(MAX_BPS) as u128;
+    // floor(asked * bps / 10000) without overflowing u128
+    let max = MAX_BPS as u128;
+    let slippage = asked / max * bps + asked % max * bps / max;
+    offered >= asked - slippage
+}
+
+// maker stake backing stake on the open side at the maker's price, rounded down
+pub fn matched_stake(stake: u64, open: u64, maker: u64) -> Option<u64> {
+    if open == 0 {
+        return None;
+    }
+    let matched = (stake as u128) * (maker as u128) / (open as u128);
+    u64::try_from(matched).ok()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PartialFill {
+    pub matched: u64,    // maker stake moved to the new bet against the taker's stake
+    pub open_left: u64,  // open stake left on the original bet
+    pub maker_left: u64, // maker stake left on the original bet
+    pub dust: u64,       // maker stake lost to rounding that backs neither bet, goes back to the maker
+}
+
+// split an offer of open against maker when stake of the open side is taken,
+// both bets end up at or below the maker's price and matched + maker_left + dust == maker
+pub fn partial_fill(stake: u64, open: u64, maker: u64) -> Option<PartialFill> {
+    if stake > open {
+        return None;
+    }
+    let matched = matched_stake(stake, open, maker)?;
+    let open_left = open - stake;
+    let maker_left = matched_stake(open_left, open, maker)?;
+    let dust = maker.checked_sub(matched)?.checked_sub(maker_left)?;
+    Some(PartialFill {
+        matched,
+        open_left,
+        maker_left,
+        dust,
+    })
+}
@@ -1,132 +1,133 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+use crate::utils::ProgramAccount;
+use crate::accounts::PartialTakerAccounts;
+use crate::error::PurebetError;
+use crate::instruction::BetIds;
+use crate::market;
+use crate::math;
+use crate::odds;
 use crate::token;
 use crate::utils;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::AccountInfo,
     clock::Clock,
     entrypoint::ProgramResult,
     msg,
-    program_error::ProgramError,
     pubkey::Pubkey,
     sysvar::Sysvar,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn bet(
     mut bet_account: utils::BetAcc,
     accounts: &[AccountInfo],
-    instruction_data: &[u8],
     program_id: &Pubkey,
+    ids: BetIds,
+    stake0: u64,
+    stake1: u64,
+    side: u8,
+    nonce: u64,
+    max_slippage_bps: u16,
 ) -> ProgramResult {
-    //get accounts
-    let accounts_iter = &mut accounts.iter();
-
-    let bet = next_account_info(accounts_iter)?;
-    let _tok_prog = next_account_info(accounts_iter)?;
-    let _source = next_account_info(accounts_iter)?;
-    let destination = next_account_info(accounts_iter)?;
-    let bettor = next_account_info(accounts_iter)?; //cant use free bet in taker order, so bettor = authority
-    let rent_payer = next_account_info(accounts_iter)?;
-    let new_bet = next_account_info(accounts_iter)?;
-
-    if new_bet.owner != program_id {
-        msg!("new bet doesn't belong to this program id");
-        return Err(ProgramError::IncorrectProgramId);
-    }
-    if !utils::correct_pool((*destination).key.to_bytes()) {
-        msg!("Incorrect pool");
-        return Err(ProgramError::InvalidArgument);
+    let accounts = PartialTakerAccounts::parse(accounts)?;
+    let (stake, counter) = odds::sides(stake0, stake1, side);
+    if stake == 0 {
+        msg!("partial taker has to stake something");
+        return Err(PurebetError::ZeroStake.into());
     }
+    let config_acc = utils::load_config(accounts.config, program_id)?;
+    market::check_open(accounts.market, program_id, &ids)?;
+    // taker has to pay into the same pool, in the same mint, as the maker
+    let pool = config_acc.bet_pool(bet_account.mint, accounts.pool)?;
+    accounts.check_mint(bet_account.mint)?;
     //check instruction data for match with original acc
-    if !utils::ids_match(&bet_account, instruction_data) {
+    if !utils::ids_match(&bet_account, &ids) {
         msg!("id information of bet and incoming matcher don't match");
-        return Err(ProgramError::InvalidInstructionData);
-    }
-    //check new bet acc for blankness
-    let mut new_bet_account = utils::BetAcc::try_from_slice(&new_bet.data.borrow())?;
-    if !utils::blank_acc(&new_bet_account) {
-        msg!("trying to start bet in non empty bet account");
-        return Err(ProgramError::AccountAlreadyInitialized);
+        return Err(PurebetError::IdMismatch.into());
     }
     //check correct side
-    let side = instruction_data[36];
     if (side == 0 && !utils::blank_wallet(bet_account.wallet0))
         || (side == 1 && !utils::blank_wallet(bet_account.wallet1))
     {
         msg!("trying to match bet on side that has already been matched");
-        return Err(ProgramError::AccountAlreadyInitialized);
+        return Err(PurebetError::SideAlreadyMatched.into());
     }
 
     if (bet_account.to_aggregate || bet_account.is_free_bet)
-        && !utils::equal_wallets(bettor.key.to_bytes(), utils::PBMM)
+        && !config_acc.is_market_maker(accounts.bettor.key.to_bytes())
     {
         msg!("not authorized to place a partial taker order on an existing unmatched free bet or bet marked for aggregation");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(PurebetError::NotMarketMaker.into());
     }
 
+    // rounding dust goes back to the maker, or to the funder for free bets like in cancel
+    let dust_owner = bet_account.refund_owner(1 - side);
+    if !token::are_paired(dust_owner, accounts.maker_destination)? {
+        msg!("wrong associated token account");
+        return Err(PurebetError::WrongTokenAccount.into());
+    }
+
+    //create new acc, keyed by the partial taker since they are the one placing it
+    utils::create_bet_account(accounts.new_bet, accounts.rent_payer, accounts.system_program, program_id, &ids, accounts.bettor.key, nonce)?;
+    let mut new_bet_account = utils::BetAcc::default();
     //set id information of new acc based on original acc
-    utils::set_bet_ids(&mut new_bet_account, instruction_data);
+    utils::set_bet_ids(&mut new_bet_account, &ids);
     //set new acc wallets
     if side == 0 {
-        new_bet_account.wallet0 = bettor.key.to_bytes();
+        new_bet_account.wallet0 = accounts.bettor.key.to_bytes();
         new_bet_account.wallet1 = bet_account.wallet1;
     } else if side == 1 {
-        new_bet_account.wallet1 = bettor.key.to_bytes();
+        new_bet_account.wallet1 = accounts.bettor.key.to_bytes();
         new_bet_account.wallet0 = bet_account.wallet0;
     }
-    new_bet_account.rent_payer = rent_payer.key.to_bytes();
+    new_bet_account.rent_payer = accounts.rent_payer.key.to_bytes();
+    new_bet_account.mint = bet_account.mint;
+    // the matched part of a free bet is still a free bet, refunded to whoever funded it
+    new_bet_account.is_free_bet = bet_account.is_free_bet;
+    new_bet_account.free_bet_side = bet_account.free_bet_side;
+    new_bet_account.free_bet_funder = bet_account.free_bet_funder;
 
     //set new acc time, might be useful
     let clock = Clock::get()?;
-    new_bet_account.placed_at = clock.unix_timestamp as u64;
+    new_bet_account.placed_at = math::timestamp(&clock)?;
+    new_bet_account.matched_at = new_bet_account.placed_at;
 
-    //handle stakes appropriately.
-    let stake0 = utils::bytes_to_num(&instruction_data, 20, 28);
-    let stake1 = utils::bytes_to_num(&instruction_data, 28, 36);
-    let mut stake: u64 = 0;
-    let mut odds: f64 = 0.0;
-    let mut original_odds: f64 = 0.0;
-    if side == 0 {
-        odds = (stake0 + stake1) as f64 / stake0 as f64;
-        original_odds =
-            (bet_account.stake0 + bet_account.stake1) as f64 / bet_account.stake0 as f64;
-        stake = stake0;
-    } else if side == 1 {
-        odds = (stake0 + stake1) as f64 / stake1 as f64;
-        original_odds =
-            (bet_account.stake0 + bet_account.stake1) as f64 / bet_account.stake1 as f64;
-        stake = stake1;
+    //handle stakes appropriately, the taker is filled at the maker's price
+    //as long as it is within the slippage they and the config allow
+    let (open, maker) = odds::sides(bet_account.stake0, bet_account.stake1, side);
+    if open == 0 {
+        msg!("nothing left open on this side of the bet");
+        return Err(PurebetError::NoOpenStake.into());
     }
-    if odds - original_odds > 0.01 {
+    let slippage_bps = max_slippage_bps.min(config_acc.odds_tolerance_bps);
+    if !odds::within_slippage(stake, counter, open, maker, slippage_bps) {
         msg!("this bettor wants odds that are too high");
-        return Err(ProgramError::InvalidInstructionData);
-    }
-
-    //ratio of original should stay the same,
-    //old acc original bettor side decrements stakes by instr data,
-    //and open side decreased by same ratio
-    if side == 0 {
-        let odds1 = (bet_account.stake0 + bet_account.stake1) as f64 / bet_account.stake1 as f64;
-        bet_account.stake1 -= stake1;
-        bet_account.stake0 = bet_account.stake1 * (odds1 - 1.0) as u64;
-    } else if side == 1 {
-        let odds0 = (bet_account.stake0 + bet_account.stake1) as f64 / bet_account.stake0 as f64;
-        bet_account.stake0 -= stake0;
-        bet_account.stake1 = bet_account.stake0 * (odds0 - 1.0) as u64;
+        return Err(PurebetError::OddsTooHigh.into());
     }
+    let fill = match odds::partial_fill(stake, open, maker) {
+        Some(fill) => fill,
+        None => {
+            msg!("partial fill is larger than the open stake");
+            return Err(PurebetError::FillTooLarge.into());
+        }
+    };
 
-    //new acc gets stakes from instr data
-    new_bet_account.stake0 = stake0;
-    new_bet_account.stake1 = stake1;
+    //new acc gets the taker's stake and the maker stake backing it,
+    //old acc keeps what is left of both sides at the same ratio
+    (new_bet_account.stake0, new_bet_account.stake1) = odds::by_side(stake, fill.matched, side);
+    (bet_account.stake0, bet_account.stake1) = odds::by_side(fill.open_left, fill.maker_left, side);
 
     new_bet_account.to_aggregate = bet_account.to_aggregate;
     bet_account.to_aggregate = false;
 
     //send tokens
-    let result = token::send(accounts, 2, 3, 4, 1, stake);
+    let result = token::send(accounts.source, accounts.pool, accounts.bettor, accounts.token_program, accounts.mint, stake);
     match result {
         Ok(_result) => {
-            bet_account.serialize(&mut *bet.data.borrow_mut())?;
-            new_bet_account.serialize(&mut *new_bet.data.borrow_mut())?;
+            if fill.dust > 0 {
+                token::send_out(accounts.pool, accounts.maker_destination, accounts.pool_authority, accounts.token_program, accounts.mint, fill.dust, pool)?;
+            }
+            bet_account.pack(&mut accounts.bet.data.borrow_mut())?;
+            new_bet_account.pack(&mut accounts.new_bet.data.borrow_mut())?;
         }
         Err(err) => {
             return Err(err);
@@ -0,0 +1,80 @@
+// grading from the final score, what each side of a market backs is described by market::MarketKind.
+// spreads and totals are in half points so half point lines never push
+use crate::error::PurebetError;
+use crate::market::MarketKind;
+use crate::utils::MarketResult;
+use solana_program::msg;
+use std::cmp::Ordering;
+
+// player props and codes that don't decode can only be settled by the oracle's posted result
+pub fn is_gradable(mkt: u16, player: u32) -> bool {
+    player == 0 && MarketKind::decode(mkt).is_some()
+}
+
+pub fn market_result(mkt: u16, player: u32, home: u32, away: u32) -> Result<MarketResult, PurebetError> {
+    // player props need more than the team scores
+    if player != 0 {
+        msg!("player markets can't be graded from a score");
+        return Err(PurebetError::UngradableMarket);
+    }
+    let kind = match MarketKind::decode(mkt) {
+        Some(kind) => kind,
+        None => {
+            msg!("mkt {} isn't a market code", mkt);
+            return Err(PurebetError::UngradableMarket);
+        }
+    };
+    let (home, away) = (i64::from(home), i64::from(away));
+    let result = match kind {
+        MarketKind::Moneyline => by_ordering(home.cmp(&away)),
+        MarketKind::Home => happened(home > away),
+        MarketKind::Away => happened(away > home),
+        MarketKind::Draw => happened(home == away),
+        MarketKind::Spread(half_points) => by_ordering((2 * home + i64::from(half_points)).cmp(&(2 * away))),
+        MarketKind::Total(half_points) => by_ordering((2 * (home + away)).cmp(&i64::from(half_points))),
+        // the half lines either side of an odd quarter line are (quarters - 1) / 2 and (quarters + 1) / 2
+        MarketKind::AsianSpread(quarters) => {
+            let spread = |half_points: i64| by_ordering((2 * home + half_points).cmp(&(2 * away)));
+            let quarters = i64::from(quarters);
+            halves(spread((quarters - 1) / 2), spread((quarters + 1) / 2))
+        }
+        MarketKind::AsianTotal(quarters) => {
+            let total = |half_points: i64| by_ordering((2 * (home + away)).cmp(&half_points));
+            let quarters = i64::from(quarters);
+            halves(total((quarters - 1) / 2), total((quarters + 1) / 2))
+        }
+    };
+    Ok(result)
+}
+
+// side 0 wins when greater, side 1 when less, equal pushes
+fn by_ordering(ordering: Ordering) -> MarketResult {
+    match ordering {
+        Ordering::Greater => MarketResult::Side0Won,
+        Ordering::Less => MarketResult::Side1Won,
+        Ordering::Equal => MarketResult::Push,
+    }
+}
+
+// combine the two halves of a quarter line, a win and a loss would cancel out but the
+// neighbouring half lines are half a point apart so whole number scores never split them that way
+fn halves(a: MarketResult, b: MarketResult) -> MarketResult {
+    match (a, b) {
+        (MarketResult::Side0Won, MarketResult::Push) | (MarketResult::Push, MarketResult::Side0Won) => {
+            MarketResult::Side0HalfWon
+        }
+        (MarketResult::Side1Won, MarketResult::Push) | (MarketResult::Push, MarketResult::Side1Won) => {
+            MarketResult::Side1HalfWon
+        }
+        (a, b) if a == b => a,
+        _ => MarketResult::Push,
+    }
+}
+
+fn happened(yes: bool) -> MarketResult {
+    if yes {
+        MarketResult::Side0Won
+    } else {
+        MarketResult::Side1Won
+    }
+}
@@ -0,0 +1,225 @@
+use crate::accounts::{GradeAccounts, PushAccounts, SettleAccounts};
+use crate::error::PurebetError;
+use crate::instruction::BetIds;
+use crate::math;
+use crate::token;
+use crate::utils::{self, MarketResult};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+};
+
+pub fn grade(
+    bet_account: utils::BetAcc,
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    ids: BetIds,
+    winner: u8,
+) -> ProgramResult {
+    let accounts = GradeAccounts::parse(accounts)?;
+    let config_acc = utils::load_config(accounts.config, program_id)?;
+    // only the admin (acting as oracle) can decide who won
+    if !config_acc.is_admin(accounts.admin.key.to_bytes()) {
+        msg!("only the admin key can grade bets");
+        return Err(PurebetError::NotAdmin.into());
+    }
+    let pool = config_acc.bet_pool(bet_account.mint, accounts.pool)?;
+    // check that id info in instruction data matches bet info
+    if !utils::ids_match(&bet_account, &ids) {
+        msg!("id information of bet and instruction data don't match");
+        return Err(PurebetError::IdMismatch.into());
+    }
+    let (winner, result) = match winner {
+        0 => (bet_account.wallet0, MarketResult::Side0Won),
+        1 => (bet_account.wallet1, MarketResult::Side1Won),
+        _ => {
+            msg!("winning side must be 0 or 1");
+            return Err(PurebetError::InvalidSide.into());
+        }
+    };
+    // the admin only pays out what the oracle already decided, so no bet on the market is paid differently
+    let market_acc = utils::load_market(accounts.market, program_id, &ids)?;
+    check_result(&market_acc, &[result])?;
+    accounts.check_mint(bet_account.mint)?;
+    if !utils::equal_wallets(accounts.rent_payer.key.to_bytes(), bet_account.rent_payer) {
+        msg!("rent can only be refunded to the original rent payer");
+        return Err(PurebetError::WrongRentPayer.into());
+    }
+
+    pay_winner(&bet_account, winner, accounts.destination, accounts.pool, accounts.pool_authority, accounts.token_program, accounts.mint, pool)?;
+
+    // close bet acc so it can't be graded twice in the same tx
+    utils::close_account(accounts.bet, accounts.rent_payer)?;
+    Ok(())
+}
+
+pub fn push(
+    bet_account: utils::BetAcc,
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    ids: BetIds,
+) -> ProgramResult {
+    let accounts = PushAccounts::parse(accounts)?;
+    let config_acc = utils::load_config(accounts.config, program_id)?;
+    // only the admin (acting as oracle) can void an event
+    if !config_acc.is_admin(accounts.admin.key.to_bytes()) {
+        msg!("only the admin key can push bets");
+        return Err(PurebetError::NotAdmin.into());
+    }
+    let pool = config_acc.bet_pool(bet_account.mint, accounts.pool)?;
+    // check that id info in instruction data matches bet info
+    if !utils::ids_match(&bet_account, &ids) {
+        msg!("id information of bet and instruction data don't match");
+        return Err(PurebetError::IdMismatch.into());
+    }
+    let market_acc = utils::load_market(accounts.market, program_id, &ids)?;
+    check_result(&market_acc, &[MarketResult::Push, MarketResult::Void])?;
+    if !utils::equal_wallets(accounts.rent_payer.key.to_bytes(), bet_account.rent_payer) {
+        msg!("rent can only be refunded to the original rent payer");
+        return Err(PurebetError::WrongRentPayer.into());
+    }
+    accounts.check_mint(bet_account.mint)?;
+
+    refund_stakes(
+        &bet_account,
+        [accounts.destination0, accounts.destination1],
+        accounts.pool,
+        accounts.pool_authority,
+        accounts.token_program,
+        accounts.mint,
+        pool,
+    )?;
+
+    // close bet acc so it can't be pushed twice in the same tx
+    utils::close_account(accounts.bet, accounts.rent_payer)?;
+    Ok(())
+}
+
+// pay out a matched bet from its market's posted result, no signer needed since the oracle already decided
+pub fn bet(bet_account: utils::BetAcc, accounts: &[AccountInfo], program_id: &Pubkey, ids: BetIds) -> ProgramResult {
+    let accounts = SettleAccounts::parse(accounts)?;
+    let config_acc = utils::load_config(accounts.config, program_id)?;
+    let pool = config_acc.bet_pool(bet_account.mint, accounts.pool)?;
+    // check that id info in instruction data matches bet info
+    if !utils::ids_match(&bet_account, &ids) {
+        msg!("id information of bet and instruction data don't match");
+        return Err(PurebetError::IdMismatch.into());
+    }
+    // same ids as the bet, so this is the bet's market
+    let market_acc = utils::load_market(accounts.market, program_id, &ids)?;
+    if !utils::equal_wallets(accounts.rent_payer.key.to_bytes(), bet_account.rent_payer) {
+        msg!("rent can only be refunded to the original rent payer");
+        return Err(PurebetError::WrongRentPayer.into());
+    }
+
+    let destinations = [accounts.destination0, accounts.destination1];
+    let wallets = [bet_account.wallet0, bet_account.wallet1];
+    match market_acc.result {
+        MarketResult::Pending => {
+            msg!("market result hasn't been posted");
+            return Err(PurebetError::MarketNotSettled.into());
+        }
+        MarketResult::Side0Won | MarketResult::Side1Won => {
+            let winner = usize::from(market_acc.result == MarketResult::Side1Won);
+            accounts.check_mint(bet_account.mint, &[destinations[winner]])?;
+            pay_winner(&bet_account, wallets[winner], destinations[winner], accounts.pool, accounts.pool_authority, accounts.token_program, accounts.mint, pool)?;
+        }
+        MarketResult::Push | MarketResult::Void => {
+            accounts.check_mint(bet_account.mint, &destinations)?;
+            refund_stakes(&bet_account, destinations, accounts.pool, accounts.pool_authority, accounts.token_program, accounts.mint, pool)?;
+        }
+        MarketResult::Side0HalfWon | MarketResult::Side1HalfWon => {
+            let winner = usize::from(market_acc.result == MarketResult::Side1HalfWon);
+            accounts.check_mint(bet_account.mint, &destinations)?;
+            split_stakes(&bet_account, winner, destinations, accounts.pool, accounts.pool_authority, accounts.token_program, accounts.mint, pool)?;
+        }
+    }
+
+    // close bet acc so it can't be settled twice in the same tx
+    utils::close_account(accounts.bet, accounts.rent_payer)
+}
+
+// the market's posted result has to be one of expected
+fn check_result(market_acc: &utils::Market, expected: &[MarketResult]) -> ProgramResult {
+    if market_acc.result == MarketResult::Pending {
+        msg!("market result hasn't been posted");
+        return Err(PurebetError::MarketNotSettled.into());
+    }
+    if !expected.contains(&market_acc.result) {
+        msg!("market result is {:?}", market_acc.result);
+        return Err(PurebetError::ResultMismatch.into());
+    }
+    Ok(())
+}
+
+// winner takes both stakes
+#[allow(clippy::too_many_arguments)]
+fn pay_winner<'a>(
+    bet_account: &utils::BetAcc,
+    winner: [u8; 32],
+    destination: &AccountInfo<'a>,
+    vault: &AccountInfo<'a>,
+    pool_authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    pool: &utils::Pool,
+) -> ProgramResult {
+    if !token::are_paired(winner, destination)? {
+        msg!("wrong associated token account");
+        return Err(PurebetError::WrongTokenAccount.into());
+    }
+    let payout = math::add(bet_account.stake0, bet_account.stake1)?;
+    token::send_out(vault, destination, pool_authority, token_program, mint, payout, pool)
+}
+
+// each side gets its own stake back, free bet stake goes back to its funder like in cancel
+#[allow(clippy::too_many_arguments)]
+fn refund_stakes<'a>(
+    bet_account: &utils::BetAcc,
+    destinations: [&AccountInfo<'a>; 2],
+    vault: &AccountInfo<'a>,
+    pool_authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    pool: &utils::Pool,
+) -> ProgramResult {
+    if !token::are_paired(bet_account.refund_owner(0), destinations[0])?
+        || !token::are_paired(bet_account.refund_owner(1), destinations[1])?
+    {
+        msg!("wrong associated token account");
+        return Err(PurebetError::WrongTokenAccount.into());
+    }
+    token::send_out(vault, destinations[0], pool_authority, token_program, mint, bet_account.stake0, pool)?;
+    token::send_out(vault, destinations[1], pool_authority, token_program, mint, bet_account.stake1, pool)
+}
+
+// quarter lines: half the bet is graded on each adjacent half line, one half wins and the other pushes,
+// so the winner gets their stake and half the loser's, the loser's pushed half is rounded down so
+// nothing is left behind in the pool
+#[allow(clippy::too_many_arguments)]
+fn split_stakes<'a>(
+    bet_account: &utils::BetAcc,
+    winner: usize,
+    destinations: [&AccountInfo<'a>; 2],
+    vault: &AccountInfo<'a>,
+    pool_authority: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    pool: &utils::Pool,
+) -> ProgramResult {
+    let loser = 1 - winner;
+    let wallets = [bet_account.wallet0, bet_account.wallet1];
+    let stakes = [bet_account.stake0, bet_account.stake1];
+    if !token::are_paired(wallets[winner], destinations[winner])?
+        || !token::are_paired(bet_account.refund_owner(loser as u8), destinations[loser])?
+    {
+        msg!("wrong associated token account");
+        return Err(PurebetError::WrongTokenAccount.into());
+    }
+    let refund = stakes[loser] / 2;
+    let payout = math::add(stakes[winner], stakes[loser] - refund)?;
+    token::send_out(vault, destinations[winner], pool_authority, token_program, mint, payout, pool)?;
+    token::send_out(vault, destinations[loser], pool_authority, token_program, mint, refund, pool)
+}
@@ -1,75 +1,85 @@
-use borsh::BorshSerialize;
+use crate::utils::ProgramAccount;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::AccountInfo,
+    clock::Clock,
     entrypoint::ProgramResult,
     msg,
-    program_error::ProgramError
+    pubkey::Pubkey,
+    sysvar::Sysvar,
 };
+use crate::accounts::TakerAccounts;
+use crate::error::PurebetError;
+use crate::instruction::BetIds;
+use crate::market;
+use crate::math;
+use crate::odds;
 use crate::utils;
 use crate::token;
 
+#[allow(clippy::too_many_arguments)]
 pub fn bet(
     mut bet_account: utils::BetAcc,
     accounts: &[AccountInfo],
-    instruction_data: &[u8],
+    program_id: &Pubkey,
+    ids: BetIds,
+    stake0: u64,
+    stake1: u64,
+    side: u8,
 ) -> ProgramResult {
     msg!("account being matched now");
-    let accounts_iter = &mut accounts.iter();
-
-    let bet = next_account_info(accounts_iter)?;
-    let _tok_prog = next_account_info(accounts_iter)?;
-    let _source = next_account_info(accounts_iter)?;
-    let destination = next_account_info(accounts_iter)?;
-    let bettor = next_account_info(accounts_iter)?; //cant use free bet in taker order, so bettor = authority
-    if !utils::correct_pool((*destination).key.to_bytes()) {
-        msg!("Incorrect pool");
-        return Err(ProgramError::InvalidArgument);
-    }
+    let accounts = TakerAccounts::parse(accounts)?;
+    let config_acc = utils::load_config(accounts.config, program_id)?;
+    market::check_open(accounts.market, program_id, &ids)?;
+    // taker has to pay into the same pool, in the same mint, as the maker
+    config_acc.bet_pool(bet_account.mint, accounts.pool)?;
+    accounts.check_mint(bet_account.mint)?;
     // sport, league, event, period, mkt, player, in instr data need to be equal to those in acc(included for websocket ig although maybe not used)
-    if !utils::ids_match(&bet_account, instruction_data){
+    if !utils::ids_match(&bet_account, &ids){
         msg!("id information of bet and incoming matcher don't match");
-        return Err(ProgramError::InvalidInstructionData);
+        return Err(PurebetError::IdMismatch.into());
     }
     // side must be correct
-    let side = instruction_data[36];
     if side == 0 && !utils::blank_wallet(bet_account.wallet0)
         || side == 1 && !utils::blank_wallet(bet_account.wallet1)
     {
         msg!("trying to match bet on side that has already been matched");
-        return Err(ProgramError::AccountAlreadyInitialized);
+        return Err(PurebetError::SideAlreadyMatched.into());
     }
 
-    let stake0 = utils::bytes_to_num(&instruction_data, 20, 28);
-    let stake1 = utils::bytes_to_num(&instruction_data, 28, 36);
-    
-    // check odds are right
-    if (side == 0 && (stake0 < bet_account.stake0 || stake1 != bet_account.stake1))
-        || (side == 1 && (stake1 < bet_account.stake1 || stake0 != bet_account.stake0))
-    {
-        msg!("this bettor wants odds are too high or profit doesn't equal what has already been bet");
-        return Err(ProgramError::InvalidInstructionData);
+    // stake to win counter is the worst price the taker accepts and stake the most they will pay,
+    // the whole open side is filled at the maker's price so only the open stake is transferred
+    let (open, maker) = odds::sides(bet_account.stake0, bet_account.stake1, side);
+    let (stake, counter) = odds::sides(stake0, stake1, side);
+    // a bet partial takers filled down to nothing would match for 0 tokens
+    if open == 0 {
+        msg!("nothing left open on this side of the bet");
+        return Err(PurebetError::NoOpenStake.into());
+    }
+    if !odds::within_price(stake, counter, open, maker) {
+        msg!("this bettor wants odds that are too high");
+        return Err(PurebetError::OddsTooHigh.into());
+    }
+    if stake < open {
+        msg!("taker stake doesn't cover the open side of the bet");
+        return Err(PurebetError::InsufficientStake.into());
     }
 
-    if (bet_account.to_aggregate || bet_account.is_free_bet) && !utils::equal_wallets(bettor.key.to_bytes(), utils::PBMM) {
+    if (bet_account.to_aggregate || bet_account.is_free_bet) && !config_acc.is_market_maker(accounts.bettor.key.to_bytes()) {
         msg!("not authorized to place a taker order on an existing unmatched free bet or bet marked for aggregation");
-        return Err(ProgramError::InvalidAccountData);
+        return Err(PurebetError::NotMarketMaker.into());
     }
     // if all checks pass, write to correct stake and wallet
-    let mut stake: u64 = 0;
     if side == 0 {
-        bet_account.stake0 = stake0;
-        bet_account.wallet0 = bettor.key.to_bytes();
-        stake = stake0;
+        bet_account.wallet0 = accounts.bettor.key.to_bytes();
     } else if side == 1 {
-        bet_account.stake1 = stake1;
-        bet_account.wallet1 = bettor.key.to_bytes();
-        stake = stake1;
+        bet_account.wallet1 = accounts.bettor.key.to_bytes();
     }
+    bet_account.matched_at = math::timestamp(&Clock::get()?)?;
     // transfer funds to pool
-    let result = token::send(accounts, 2, 3, 4, 1, stake);
+    let result = token::send(accounts.source, accounts.pool, accounts.bettor, accounts.token_program, accounts.mint, open);
     match result {
         Ok(_result) => {
-            bet_account.serialize(&mut *bet.data.borrow_mut())?;
+            bet_account.pack(&mut accounts.bet.data.borrow_mut())?;
         }
         Err(err) => {
             return Err(err);
@@ -1,15 +1,22 @@
 use solana_program::{
-    account_info::AccountInfo, 
-    entrypoint::ProgramResult, 
-    msg, 
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
     program::invoke,
     program_error::ProgramError,
-    program_pack::Pack,
     program::invoke_signed,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
 };
 
-use spl_token::instruction::transfer;
-use spl_token::state::Account as TokenAccount;
+use spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use spl_token_2022::instruction::{initialize_account3, transfer_checked};
+use spl_token_2022::state::{Account as TokenAccount, Mint};
+use crate::error::PurebetError;
+use crate::math;
 use crate::utils;
 
 const OFFICIAL_TOK_PROG: [u8; 32] = [
@@ -17,43 +24,110 @@ const OFFICIAL_TOK_PROG: [u8; 32] = [
     95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
 ]; // could also use program id provdied in spl_token crate
 
+// pools can hold mints from either the original token program or token-2022
+pub fn is_token_program(key: &Pubkey) -> bool {
+    utils::equal_wallets(key.to_bytes(), OFFICIAL_TOK_PROG) || *key == spl_token_2022::id()
+}
+
+// token-2022 accounts share the original layout and only append extensions, so one unpack covers both
 pub fn are_paired(auth:[u8;32], tok:&AccountInfo) -> Result<bool, ProgramError>{
-    let mut data: &[u8] = &tok.try_borrow_data()?;
-    let acc = TokenAccount::unpack(&mut data)?;
-    return Ok(utils::equal_wallets(auth, acc.owner.to_bytes()));
+    let data: &[u8] = &tok.try_borrow_data()?;
+    let acc = StateWithExtensions::<TokenAccount>::unpack(data)?;
+    Ok(utils::equal_wallets(auth, acc.base.owner.to_bytes()))
+}
+
+pub fn mint_of(tok:&AccountInfo) -> Result<[u8;32], ProgramError>{
+    if !is_token_program(tok.owner) {
+        msg!("not a token account");
+        return Err(PurebetError::IncorrectTokenProgram.into());
+    }
+    let data: &[u8] = &tok.try_borrow_data()?;
+    let acc = StateWithExtensions::<TokenAccount>::unpack(data)?;
+    Ok(acc.base.mint.to_bytes())
+}
+
+// make sure a token account sending to or receiving from the pool holds the bet's mint
+pub fn check_mint(tok:&AccountInfo, mint:[u8;32]) -> ProgramResult{
+    if !utils::equal_wallets(mint_of(tok)?, mint) {
+        msg!("token account mint doesn't match the bet");
+        return Err(PurebetError::MintMismatch.into());
+    }
+    Ok(())
+}
+
+// space a token account in mint needs, token-2022 mints can require account extensions
+pub fn vault_len(mint: &AccountInfo) -> Result<usize, ProgramError> {
+    let data: &[u8] = &mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(data)?;
+    let account_extensions = ExtensionType::get_required_init_account_extensions(&mint_state.get_extension_types()?);
+    ExtensionType::try_calculate_account_len::<TokenAccount>(&account_extensions)
 }
 
-pub fn send(
-    accounts: &[AccountInfo],
-    source_ind: usize,
-    dest_ind: usize,
-    auth_ind: usize,
-    tok_prog_ind: usize,
+// initialize an already allocated token account in mint, owned by owner
+pub fn init_vault<'a>(
+    vault: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    owner: &Pubkey,
+    tok_prog: &AccountInfo<'a>,
+) -> ProgramResult {
+    let ix = initialize_account3(tok_prog.key, vault.key, mint.key, owner)?;
+    invoke(&ix, &[vault.clone(), mint.clone(), tok_prog.clone()])?;
+    Ok(())
+}
+
+// decimals for transfer_checked, and the amount that has to leave the source so that
+// `credit` arrives after a transfer fee extension takes its cut
+fn gross_up(mint: &AccountInfo, credit: u64) -> Result<(u8, u64), ProgramError> {
+    let data: &[u8] = &mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<Mint>::unpack(data)?;
+    let fee = match mint_state.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => {
+            let epoch = Clock::get()?.epoch;
+            match fee_config.calculate_inverse_epoch_fee(epoch, credit) {
+                Some(fee) => fee,
+                None => {
+                    msg!("transfer fee overflow");
+                    return Err(PurebetError::ArithmeticOverflow.into());
+                }
+            }
+        }
+        Err(_) => 0,
+    };
+    let amnt = math::add(credit, fee)?;
+    Ok((mint_state.base.decimals, amnt))
+}
+
+// pays into the pool, amnt is what the pool is credited with, fees on top come out of the source
+pub fn send<'a>(
+    source: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    tok_prog: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
     amnt: u64,
 ) -> ProgramResult {
     msg!("transferring tokens");
-    let source = &accounts[source_ind];
-    let destination = &accounts[dest_ind];
-    let authority = &accounts[auth_ind];
-    let tok_prog = &accounts[tok_prog_ind];
-
-    if !utils::equal_wallets((tok_prog).key.to_bytes(), OFFICIAL_TOK_PROG) {
+    if !is_token_program(tok_prog.key) {
         msg!("Incorrect token program id");
-        return Err(ProgramError::IncorrectProgramId);
+        return Err(PurebetError::IncorrectTokenProgram.into());
     }
+    let (decimals, amnt) = gross_up(mint, amnt)?;
 
-    let ix = transfer(
+    let ix = transfer_checked(
         tok_prog.key,
         source.key,
+        mint.key,
         destination.key,
         authority.key,
-        &[&authority.key],
+        &[authority.key],
         amnt,
+        decimals,
     )?;
     invoke(
         &ix,
         &[
             source.clone(),
+            mint.clone(),
             destination.clone(),
             authority.clone(),
             tok_prog.clone(),
@@ -62,42 +136,43 @@ pub fn send(
     Ok(())
 }
 
-// seed and bump key for transfering tokens will be hard coded to program
-pub fn send_out(
-    accounts: &[AccountInfo],
-    source_ind: usize,
-    dest_ind: usize,
-    auth_ind: usize,
-    tok_prog_ind: usize,
+// pool pda signs with the "pool" seed, the pool's mint and the bump stored in the config,
+// any transfer fee is taken from what the receiver gets
+pub fn send_out<'a>(
+    source: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    tok_prog: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
     amnt: u64,
+    pool: &utils::Pool,
 ) -> ProgramResult {
-    let source = &accounts[source_ind];
-    let destination = &accounts[dest_ind];
-    let authority = &accounts[auth_ind];
-    let tok_prog = &accounts[tok_prog_ind];
-
-    if !utils::equal_wallets((tok_prog).key.to_bytes(), OFFICIAL_TOK_PROG) {
+    if !is_token_program(tok_prog.key) {
         msg!("Incorrect token program id");
-        return Err(ProgramError::IncorrectProgramId);
+        return Err(PurebetError::IncorrectTokenProgram.into());
     }
+    let decimals = StateWithExtensions::<Mint>::unpack(&mint.try_borrow_data()?)?.base.decimals;
 
-    let ix = transfer(
+    let ix = transfer_checked(
         tok_prog.key,
         source.key,
+        mint.key,
         destination.key,
         authority.key,
-        &[&authority.key],
+        &[authority.key],
         amnt,
+        decimals,
     )?;
     invoke_signed(
         &ix,
         &[
-            source.clone(), 
-            destination.clone(), 
-            authority.clone(), 
+            source.clone(),
+            mint.clone(),
+            destination.clone(),
+            authority.clone(),
             tok_prog.clone()
         ],
-        &[ &[ b"pool", &[255]]] ,
+        &[ &[ b"pool", &pool.mint, &[pool.bump]]] ,
     )?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,12 +1,72 @@
+use crate::error::PurebetError;
+use crate::instruction::{BetIds, EventIds};
+use crate::math;
 use borsh::{BorshDeserialize, BorshSerialize};
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+
+// first byte of every program owned account, a zeroed account reads as Uninitialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AccountType {
+    Uninitialized = 0,
+    Bet = 1,
+    CancelDelay = 2,
+    Config = 3,
+    Market = 4,
+    Score = 5,
+}
+
+// account type then layout version, written in front of the borsh data
+pub const HEADER_LEN: usize = 2;
+
+// program owned account data, always read and written through unpack and pack so
+// one kind of account can never be deserialized as another
+pub trait ProgramAccount: BorshSerialize + BorshDeserialize {
+    const ACCOUNT_TYPE: AccountType;
+    const VERSION: u8;
+
+    fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < HEADER_LEN || data[0] != Self::ACCOUNT_TYPE as u8 {
+            msg!("account isn't a {:?} account", Self::ACCOUNT_TYPE);
+            return Err(PurebetError::WrongAccountType.into());
+        }
+        if data[1] != Self::VERSION {
+            msg!("unsupported {:?} account version {}", Self::ACCOUNT_TYPE, data[1]);
+            return Err(PurebetError::WrongAccountVersion.into());
+        }
+        Ok(Self::try_from_slice(&data[HEADER_LEN..])?)
+    }
+
+    fn pack(&self, data: &mut [u8]) -> ProgramResult {
+        if data.len() < HEADER_LEN {
+            msg!("account too small for a {:?} account", Self::ACCOUNT_TYPE);
+            return Err(PurebetError::WrongAccountType.into());
+        }
+        data[0] = Self::ACCOUNT_TYPE as u8;
+        data[1] = Self::VERSION;
+        self.serialize(&mut &mut data[HEADER_LEN..])?;
+        Ok(())
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
 pub struct BetAcc {
     pub sport: u8,
     pub league: u32,
     pub event: u64,
     pub period: u8,  //need rules for this, 0,, 1, 2...
-    pub mkt: u16, //0 for moneyline, 1 for home, 2 for away, 3 for draw, 200 + spread * 2, 1000 + total * 2
-    pub player: u32, //4 bytes of first initial, last initial, 2nd letter of last name, 3rd letter of last name, count spaces (ex de xxx), if blank set to 0
+    pub mkt: u16, // market::MarketKind code, 0 for moneyline, 1 for home, 2 for away, 3 for draw, 200 + spread * 2, asian quarter lines 400 to 999, 1000 + total * 2
+    pub player: u32, // market::PlayerCode code, 0 if blank
     pub stake0: u64,
     pub stake1: u64,
     pub wallet0: [u8; 32],
@@ -15,26 +75,580 @@ pub struct BetAcc {
     pub is_free_bet: bool,
     pub placed_at: u64, //https://stackoverflow.com/questions/72223450/how-to-get-the-current-time-in-solana-program-without-using-any-external-systemp
     pub to_aggregate: bool,
+    pub mint: [u8; 32], // mint of the pool both stakes are held in
+    pub matched_at: u64, // when both sides got a bettor, 0 until then
+    pub free_bet_side: u8, // side the free bet is on, only read when is_free_bet
+    pub free_bet_funder: [u8; 32], // gets the free bet's stake back instead of its bettor, blank unless is_free_bet
+}
+
+// header plus borsh size of BetAcc, bet accounts are created with exactly this much space
+pub const BET_ACC_LEN: usize = BET_ACC_V2_LEN + 1 + 32;
+
+// new fields only go at the end of BetAcc along with a version bump, older bets keep their
+// layout until they are migrated and every other instruction rejects them until then
+impl ProgramAccount for BetAcc {
+    const ACCOUNT_TYPE: AccountType = AccountType::Bet;
+    const VERSION: u8 = 3;
+}
+
+impl BetAcc {
+    // who a refund of side's stake goes to, the free bet's funder for the free bet side
+    pub fn refund_owner(&self, side: u8) -> [u8; 32] {
+        if self.is_free_bet && side == self.free_bet_side {
+            self.free_bet_funder
+        } else if side == 0 {
+            self.wallet0
+        } else {
+            self.wallet1
+        }
+    }
+}
+
+// BetAcc before the free bet side and funder were recorded
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct BetAccV2 {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+    pub mkt: u16,
+    pub player: u32,
+    pub stake0: u64,
+    pub stake1: u64,
+    pub wallet0: [u8; 32],
+    pub wallet1: [u8; 32],
+    pub rent_payer: [u8; 32],
+    pub is_free_bet: bool,
+    pub placed_at: u64,
+    pub to_aggregate: bool,
+    pub mint: [u8; 32],
+    pub matched_at: u64,
+}
+
+pub const BET_ACC_V2_LEN: usize = BET_ACC_V1_LEN + 8;
+
+impl ProgramAccount for BetAccV2 {
+    const ACCOUNT_TYPE: AccountType = AccountType::Bet;
+    const VERSION: u8 = 2;
+}
+
+impl BetAccV2 {
+    // v2 decided the free bet side from the market makers in the config when it was needed,
+    // the caller works it out once and the funder is the rent payer, who cancels refunded
+    pub fn upgrade(self, free_bet_side: u8) -> BetAcc {
+        BetAcc {
+            sport: self.sport,
+            league: self.league,
+            event: self.event,
+            period: self.period,
+            mkt: self.mkt,
+            player: self.player,
+            stake0: self.stake0,
+            stake1: self.stake1,
+            wallet0: self.wallet0,
+            wallet1: self.wallet1,
+            rent_payer: self.rent_payer,
+            is_free_bet: self.is_free_bet,
+            placed_at: self.placed_at,
+            to_aggregate: self.to_aggregate,
+            mint: self.mint,
+            matched_at: self.matched_at,
+            free_bet_side: if self.is_free_bet { free_bet_side } else { 0 },
+            free_bet_funder: if self.is_free_bet { self.rent_payer } else { [0; 32] },
+        }
+    }
+}
+
+// BetAcc before matched_at was added
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct BetAccV1 {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+    pub mkt: u16,
+    pub player: u32,
+    pub stake0: u64,
+    pub stake1: u64,
+    pub wallet0: [u8; 32],
+    pub wallet1: [u8; 32],
+    pub rent_payer: [u8; 32],
+    pub is_free_bet: bool,
+    pub placed_at: u64,
+    pub to_aggregate: bool,
+    pub mint: [u8; 32],
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub const BET_ACC_V1_LEN: usize = HEADER_LEN + 1 + 4 + 8 + 1 + 2 + 4 + 8 + 8 + 32 + 32 + 32 + 1 + 8 + 1 + 32;
+
+impl ProgramAccount for BetAccV1 {
+    const ACCOUNT_TYPE: AccountType = AccountType::Bet;
+    const VERSION: u8 = 1;
+}
+
+impl From<BetAccV1> for BetAccV2 {
+    fn from(v1: BetAccV1) -> Self {
+        BetAccV2 {
+            sport: v1.sport,
+            league: v1.league,
+            event: v1.event,
+            period: v1.period,
+            mkt: v1.mkt,
+            player: v1.player,
+            stake0: v1.stake0,
+            stake1: v1.stake1,
+            wallet0: v1.wallet0,
+            wallet1: v1.wallet1,
+            rent_payer: v1.rent_payer,
+            is_free_bet: v1.is_free_bet,
+            placed_at: v1.placed_at,
+            to_aggregate: v1.to_aggregate,
+            mint: v1.mint,
+            // v1 didn't record it, matched bets get the time they were placed as the closest known
+            matched_at: if blank_wallet(v1.wallet0) || blank_wallet(v1.wallet1) { 0 } else { v1.placed_at },
+        }
+    }
+}
+
+// bets from before accounts had a header, plain borsh with no type or version and no mint,
+// their stakes all went to the single hardcoded LEGACY_POOL token account
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct BetAccV0 {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+    pub mkt: u16,
+    pub player: u32,
+    pub stake0: u64,
+    pub stake1: u64,
+    pub wallet0: [u8; 32],
+    pub wallet1: [u8; 32],
+    pub rent_payer: [u8; 32],
+    pub is_free_bet: bool,
+    pub placed_at: u64,
+    pub to_aggregate: bool,
+}
+
+// no header, so a program owned account of exactly this size is taken to be one of these
+pub const BET_ACC_V0_LEN: usize = 1 + 4 + 8 + 1 + 2 + 4 + 8 + 8 + 32 + 32 + 32 + 1 + 8 + 1;
+
+// the pool token account every headerless bet's stakes were sent to
+pub const LEGACY_POOL: [u8; 32] = [
+    36, 72, 94, 114, 132, 225, 24, 60, 198, 3, 99, 170, 222, 13, 99, 85, 216, 113, 112, 141, 102, 74, 146, 96, 56, 36,
+    11, 95, 123, 3, 27, 194,
+];
+
+impl BetAccV0 {
+    // mint is whichever mint the config's pool for LEGACY_POOL holds
+    pub fn upgrade(self, mint: [u8; 32]) -> BetAccV1 {
+        BetAccV1 {
+            sport: self.sport,
+            league: self.league,
+            event: self.event,
+            period: self.period,
+            mkt: self.mkt,
+            player: self.player,
+            stake0: self.stake0,
+            stake1: self.stake1,
+            wallet0: self.wallet0,
+            wallet1: self.wallet1,
+            rent_payer: self.rent_payer,
+            is_free_bet: self.is_free_bet,
+            placed_at: self.placed_at,
+            to_aggregate: self.to_aggregate,
+            mint,
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
 pub struct CancelDelay {
     pub is_real: bool,
     pub seconds: u8,
 }
 
-pub const EMPTY_WALLET: [u8; 32] = [0; 32];
+pub const CANCEL_DELAY_LEN: usize = HEADER_LEN + 1 + 1;
 
-pub const POOL: [u8; 32] = [
-    36, 72, 94, 114, 132, 225, 24, 60, 198, 3, 99, 170, 222, 13, 99, 85, 216, 113, 112, 141, 102,
-    74, 146, 96, 56, 36, 11, 95, 123, 3, 27, 194,
-];
+impl ProgramAccount for CancelDelay {
+    const ACCOUNT_TYPE: AccountType = AccountType::CancelDelay;
+    const VERSION: u8 = 1;
+}
 
-pub const PBMM: [u8; 32] = [
-    5, 202, 24, 56, 131, 111, 203, 156, 106, 68, 185, 161, 229, 194, 11, 95, 141, 149, 209, 42, 8,
-    93, 165, 215, 83, 97, 15, 14, 27, 207, 86, 178,
-];
+impl CancelDelay {
+    // the admin creates the delay account empty, a zeroed account of the right size is a new one
+    pub fn unpack_or_new(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() == CANCEL_DELAY_LEN && data.iter().all(|b| *b == 0) {
+            return Ok(Self::default());
+        }
+        Self::unpack(data)
+    }
+}
+
+// market accounts are pdas of the six ids that describe the market
+pub struct MarketSeeds {
+    sport: [u8; 1],
+    league: [u8; 4],
+    event: [u8; 8],
+    period: [u8; 1],
+    mkt: [u8; 2],
+    player: [u8; 4],
+}
+
+impl MarketSeeds {
+    pub fn new(ids: &BetIds) -> Self {
+        MarketSeeds {
+            sport: [ids.sport],
+            league: ids.league.to_le_bytes(),
+            event: ids.event.to_le_bytes(),
+            period: [ids.period],
+            mkt: ids.mkt.to_le_bytes(),
+            player: ids.player.to_le_bytes(),
+        }
+    }
+
+    pub fn seeds(&self) -> [&[u8]; 7] {
+        [
+            b"market",
+            &self.sport,
+            &self.league,
+            &self.event,
+            &self.period,
+            &self.mkt,
+            &self.player,
+        ]
+    }
+}
+
+pub fn find_market_address(ids: &BetIds, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&MarketSeeds::new(ids).seeds(), program_id)
+}
+
+// final score of an event's period, posted once by the oracle and shared by every market on it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct Score {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+    pub home: u32,
+    pub away: u32,
+}
+
+pub const SCORE_LEN: usize = HEADER_LEN + 1 + 4 + 8 + 1 + 4 + 4;
+
+impl ProgramAccount for Score {
+    const ACCOUNT_TYPE: AccountType = AccountType::Score;
+    const VERSION: u8 = 1;
+}
 
+impl Score {
+    pub fn event_ids(&self) -> EventIds {
+        EventIds {
+            sport: self.sport,
+            league: self.league,
+            event: self.event,
+            period: self.period,
+        }
+    }
+}
+
+// score pda seeds, the market seeds without mkt and player
+pub struct ScoreSeeds {
+    sport: [u8; 1],
+    league: [u8; 4],
+    event: [u8; 8],
+    period: [u8; 1],
+}
+
+impl ScoreSeeds {
+    pub fn new(ids: &EventIds) -> Self {
+        ScoreSeeds {
+            sport: [ids.sport],
+            league: ids.league.to_le_bytes(),
+            event: ids.event.to_le_bytes(),
+            period: [ids.period],
+        }
+    }
+
+    pub fn seeds(&self) -> [&[u8]; 5] {
+        [b"score", &self.sport, &self.league, &self.event, &self.period]
+    }
+}
+
+pub fn find_score_address(ids: &EventIds, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&ScoreSeeds::new(ids).seeds(), program_id)
+}
+
+// bet accounts are pdas of their market ids, the maker's wallet and a nonce picked by the maker,
+// so indexers can derive them and makers can't be front run into a pre populated account
+pub struct BetSeeds {
+    market: MarketSeeds,
+    maker: [u8; 32],
+    nonce: [u8; 8],
+}
+
+impl BetSeeds {
+    pub fn new(ids: &BetIds, maker: &Pubkey, nonce: u64) -> Self {
+        BetSeeds {
+            market: MarketSeeds::new(ids),
+            maker: maker.to_bytes(),
+            nonce: nonce.to_le_bytes(),
+        }
+    }
+
+    pub fn seeds(&self) -> [&[u8]; 9] {
+        [
+            b"bet",
+            &self.market.sport,
+            &self.market.league,
+            &self.market.event,
+            &self.market.period,
+            &self.market.mkt,
+            &self.market.player,
+            &self.maker,
+            &self.nonce,
+        ]
+    }
+}
+
+pub fn find_bet_address(ids: &BetIds, maker: &Pubkey, nonce: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&BetSeeds::new(ids, maker, nonce).seeds(), program_id)
+}
+
+// orders and matches are only taken while a market is open and before it starts,
+// settled is final and only reached by posting the result
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MarketStatus {
+    #[default]
+    Open,
+    Suspended,
+    Closed,
+    Settled,
+}
+
+// posted once by the oracle, push and void both return each side's stake,
+// half won is a quarter line where half the bet won and the other half pushed
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MarketResult {
+    #[default]
+    Pending,
+    Side0Won,
+    Side1Won,
+    Push,
+    Void,
+    Side0HalfWon,
+    Side1HalfWon,
+}
+
+// one per set of ids, created by the admin before any bet on it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct Market {
+    pub sport: u8,
+    pub league: u32,
+    pub event: u64,
+    pub period: u8,
+    pub mkt: u16,
+    pub player: u32,
+    pub status: MarketStatus,
+    pub start_time: u64, // unix timestamp, no new orders or matches from then on
+    pub result: MarketResult,
+}
+
+pub const MARKET_LEN: usize = HEADER_LEN + 1 + 4 + 8 + 1 + 2 + 4 + 1 + 8 + 1;
+
+impl ProgramAccount for Market {
+    const ACCOUNT_TYPE: AccountType = AccountType::Market;
+    const VERSION: u8 = 1;
+}
+
+impl Market {
+    pub fn ids(&self) -> BetIds {
+        BetIds {
+            sport: self.sport,
+            league: self.league,
+            event: self.event,
+            period: self.period,
+            mkt: self.mkt,
+            player: self.player,
+        }
+    }
+}
+
+// deserialize the market a bet instruction references, making sure it is this program's market for ids
+pub fn load_market(market: &AccountInfo, program_id: &Pubkey, ids: &BetIds) -> Result<Market, ProgramError> {
+    if market.owner != program_id {
+        msg!("market doesn't belong to this program id");
+        return Err(PurebetError::IncorrectOwner.into());
+    }
+    // markets are only ever created at the pda of their ids, so matching ids means it is the right one
+    let market_acc = Market::unpack(&market.data.borrow())?;
+    if market_acc.ids() != *ids {
+        msg!("id information of market and instruction data don't match");
+        return Err(PurebetError::IdMismatch.into());
+    }
+    Ok(market_acc)
+}
+
+pub const MAX_MARKET_MAKERS: usize = 4;
+pub const MAX_POOLS: usize = 8;
+
+// one vault per whitelisted mint, owned by the pda with seeds "pool", mint and bump
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, Clone, Copy)]
+pub struct Pool {
+    pub mint: [u8; 32],
+    pub vault: [u8; 32], // token account stakes in this mint are sent to
+    pub bump: u8,
+}
+
+// single program owned pda (seed "config") holding the keys that used to be hardcoded
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct Config {
+    pub is_initialized: bool,
+    pub admin: [u8; 32],
+    pub market_makers: [[u8; 32]; MAX_MARKET_MAKERS], // unused slots are left blank
+    pub pools: [Pool; MAX_POOLS], // unused slots have a blank mint
+    pub odds_tolerance_bps: u16, // most a partial taker's fill can be worse than the price they asked for
+    pub oracle: [u8; 32], // posts market results, blank until the admin sets one
+}
+
+pub const CONFIG_LEN: usize = CONFIG_V1_LEN + 32;
+
+impl ProgramAccount for Config {
+    const ACCOUNT_TYPE: AccountType = AccountType::Config;
+    const VERSION: u8 = 2;
+}
+
+// Config before the oracle was added
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default)]
+pub struct ConfigV1 {
+    pub is_initialized: bool,
+    pub admin: [u8; 32],
+    pub market_makers: [[u8; 32]; MAX_MARKET_MAKERS],
+    pub pools: [Pool; MAX_POOLS],
+    pub odds_tolerance_bps: u16,
+}
+
+pub const CONFIG_V1_LEN: usize = HEADER_LEN + 1 + 32 + 32 * MAX_MARKET_MAKERS + (32 + 32 + 1) * MAX_POOLS + 2;
+
+impl ProgramAccount for ConfigV1 {
+    const ACCOUNT_TYPE: AccountType = AccountType::Config;
+    const VERSION: u8 = 1;
+}
+
+impl From<ConfigV1> for Config {
+    fn from(v1: ConfigV1) -> Self {
+        Config {
+            is_initialized: v1.is_initialized,
+            admin: v1.admin,
+            market_makers: v1.market_makers,
+            pools: v1.pools,
+            odds_tolerance_bps: v1.odds_tolerance_bps,
+            // no results can be posted until the admin picks an oracle
+            oracle: [0; 32],
+        }
+    }
+}
+
+impl Config {
+    pub fn is_admin(&self, wallet: [u8; 32]) -> bool {
+        equal_wallets(wallet, self.admin)
+    }
+    pub fn is_oracle(&self, wallet: [u8; 32]) -> bool {
+        !blank_wallet(self.oracle) && equal_wallets(wallet, self.oracle)
+    }
+    pub fn is_market_maker(&self, wallet: [u8; 32]) -> bool {
+        !blank_wallet(wallet) && self.market_makers.iter().any(|mm| equal_wallets(wallet, *mm))
+    }
+    pub fn pool_for_mint(&self, mint: [u8; 32]) -> Option<&Pool> {
+        if blank_wallet(mint) {
+            return None;
+        }
+        self.pools.iter().find(|p| equal_wallets(p.mint, mint))
+    }
+    pub fn pool_for_vault(&self, vault: [u8; 32]) -> Option<&Pool> {
+        self.pools
+            .iter()
+            .find(|p| !blank_wallet(p.mint) && equal_wallets(p.vault, vault))
+    }
+    // register the pool for its mint, replacing the mint's old vault or taking a free slot
+    pub fn set_pool(&mut self, pool: Pool) -> ProgramResult {
+        let slot = match self.pools.iter().position(|p| equal_wallets(p.mint, pool.mint)) {
+            Some(slot) => slot,
+            None => match self.pools.iter().position(|p| blank_wallet(p.mint)) {
+                Some(slot) => slot,
+                None => {
+                    msg!("no free pool slots left in the config");
+                    return Err(PurebetError::PoolsFull.into());
+                }
+            },
+        };
+        self.pools[slot] = pool;
+        Ok(())
+    }
+    // pool a bet in mint has to be using, vault being the pool token account that was passed in
+    pub fn bet_pool(&self, mint: [u8; 32], vault: &AccountInfo) -> Result<&Pool, ProgramError> {
+        let pool = match self.pool_for_mint(mint) {
+            Some(pool) => pool,
+            None => {
+                msg!("mint isn't whitelisted");
+                return Err(PurebetError::MintNotWhitelisted.into());
+            }
+        };
+        if !equal_wallets(vault.key.to_bytes(), pool.vault) {
+            msg!("Incorrect pool");
+            return Err(PurebetError::IncorrectPool.into());
+        }
+        Ok(pool)
+    }
+}
+
+pub fn find_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], program_id)
+}
+
+// pda owning the mint's vault, the bump it returns is the one stored in the pool
+pub fn find_pool_authority(mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool", mint.as_ref()], program_id)
+}
+
+// vault token account created by InitVault, itself a pda so the program can create it
+pub fn find_vault_address(mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault", mint.as_ref()], program_id)
+}
+
+// deserialize a posted score, making sure it is the one for the event and period
+pub fn load_score(score: &AccountInfo, program_id: &Pubkey, ids: &EventIds) -> Result<Score, ProgramError> {
+    if score.owner != program_id {
+        msg!("score hasn't been posted");
+        return Err(PurebetError::IncorrectOwner.into());
+    }
+    // scores are only ever created at the pda of their event, same as markets
+    let score_acc = Score::unpack(&score.data.borrow())?;
+    if score_acc.event_ids() != *ids {
+        msg!("id information of score and market don't match");
+        return Err(PurebetError::IdMismatch.into());
+    }
+    Ok(score_acc)
+}
+
+// deserialize the config, making sure it is this program's real config account
+pub fn load_config(config: &AccountInfo, program_id: &Pubkey) -> Result<Config, ProgramError> {
+    if *config.key != find_config_address(program_id).0 || config.owner != program_id {
+        msg!("incorrect config account");
+        return Err(PurebetError::IncorrectConfig.into());
+    }
+    let config_acc = Config::unpack(&config.data.borrow())?;
+    if !config_acc.is_initialized {
+        msg!("config account isn't initialized");
+        return Err(PurebetError::IncorrectConfig.into());
+    }
+    Ok(config_acc)
+}
+
+pub const EMPTY_WALLET: [u8; 32] = [0; 32];
+
+// only used to initialize the config account, after that the admin comes from the config
 pub const ADMIN:[u8; 32] = [232, 166, 95, 126, 248, 155, 162, 93, 189, 238, 126, 247, 103, 87, 122, 15, 74, 245, 250, 181, 251, 116, 215, 190, 226, 34, 136, 11, 108, 33, 242, 149];
 
 pub fn equal_wallets(wallet1: [u8; 32], wallet2: [u8; 32]) -> bool {
@@ -43,51 +657,114 @@ pub fn equal_wallets(wallet1: [u8; 32], wallet2: [u8; 32]) -> bool {
             return false;
         }
     }
-    return true;
-}
-pub fn correct_pool(wallet: [u8; 32]) -> bool {
-    return equal_wallets(wallet, POOL);
+    true
 }
 pub fn blank_wallet(wallet: [u8; 32]) -> bool {
-    return equal_wallets(wallet, EMPTY_WALLET);
+    equal_wallets(wallet, EMPTY_WALLET)
 }
 
-pub fn bytes_to_num(data: &[u8], start: usize, end: usize) -> u64 {
-    let mut output: u64 = 0;
-    let max_pow = end - start;
-    for i in 0..max_pow {
-        output = output + data[start + i] as u64 * u64::pow(256, i as u32);
-    }
-    return output;
-}
-
-pub fn ids_match(bet_account: &BetAcc, instruction_data: &[u8]) -> bool {
-    let sports_equal = instruction_data[0] == bet_account.sport;
-    let leagues_equal = bytes_to_num(instruction_data, 1, 5) as u32 == bet_account.league;
-    let events_equal = bytes_to_num(instruction_data, 5, 13) == bet_account.event;
-    let periods_equal = instruction_data[13] == bet_account.period;
-    let mkts_equal = bytes_to_num(instruction_data, 14, 16) as u16 == bet_account.mkt;
-    let players_equal = bytes_to_num(instruction_data, 16, 20) as u32 == bet_account.player;
-    return sports_equal
+pub fn ids_match(bet_account: &BetAcc, ids: &BetIds) -> bool {
+    let sports_equal = ids.sport == bet_account.sport;
+    let leagues_equal = ids.league == bet_account.league;
+    let events_equal = ids.event == bet_account.event;
+    let periods_equal = ids.period == bet_account.period;
+    let mkts_equal = ids.mkt == bet_account.mkt;
+    let players_equal = ids.player == bet_account.player;
+    sports_equal
         && leagues_equal
         && events_equal
         && periods_equal
         && mkts_equal
-        && players_equal;
+        && players_equal
+}
+
+pub fn set_bet_ids(bet_account: &mut BetAcc, ids: &BetIds) {
+    bet_account.sport = ids.sport;
+    bet_account.league = ids.league;
+    bet_account.event = ids.event;
+    bet_account.period = ids.period;
+    bet_account.mkt = ids.mkt;
+    bet_account.player = ids.player;
+}
+
+// create a bet pda owned by this program, rent paid by rent_payer
+pub fn create_bet_account<'a>(
+    bet: &AccountInfo<'a>,
+    rent_payer: &AccountInfo<'a>,
+    system_prog: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    ids: &BetIds,
+    maker: &Pubkey,
+    nonce: u64,
+) -> ProgramResult {
+    let bet_seeds = BetSeeds::new(ids, maker, nonce);
+    let seeds = bet_seeds.seeds();
+    let (address, bump) = Pubkey::find_program_address(&seeds, program_id);
+    if address != *bet.key {
+        msg!("bet account isn't derived from its ids, maker and nonce");
+        return Err(PurebetError::WrongBetAddress.into());
+    }
+    if bet.owner != &system_program::id() || !bet.data_is_empty() {
+        msg!("trying to start bet in non empty bet account");
+        return Err(PurebetError::BetAccountNotEmpty.into());
+    }
+    let bump = [bump];
+    let signer_seeds = [&seeds[..], &[&bump[..]]].concat();
+    create_pda_account(bet, rent_payer, system_prog, program_id, BET_ACC_LEN, &signer_seeds)
 }
 
-pub fn blank_acc(bet_account: &BetAcc) -> bool {
-    let wallet0_empty = blank_wallet(bet_account.wallet0);
-    let wallet1_empty = blank_wallet(bet_account.wallet1);
-    let rent_payer_empty = blank_wallet(bet_account.rent_payer);
-    return wallet0_empty && wallet1_empty && rent_payer_empty;
+// create a program owned pda with space bytes, signer_seeds must include the bump
+pub fn create_pda_account<'a>(
+    account: &AccountInfo<'a>,
+    rent_payer: &AccountInfo<'a>,
+    system_prog: &AccountInfo<'a>,
+    program_id: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    if !rent_payer.is_signer {
+        msg!("rent payer isn't signing");
+        return Err(PurebetError::RentPayerNotSigner.into());
+    }
+    let rent = Rent::get()?.minimum_balance(space);
+    let current_lamports = account.lamports();
+
+    if current_lamports == 0 {
+        invoke_signed(
+            &system_instruction::create_account(rent_payer.key, account.key, rent, space as u64, program_id),
+            &[rent_payer.clone(), account.clone(), system_prog.clone()],
+            &[signer_seeds],
+        )?;
+    } else {
+        // someone sent lamports to the address first, create_account would fail so top up, allocate and assign instead
+        if current_lamports < rent {
+            invoke(
+                &system_instruction::transfer(rent_payer.key, account.key, rent - current_lamports),
+                &[rent_payer.clone(), account.clone(), system_prog.clone()],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(account.key, space as u64),
+            &[account.clone(), system_prog.clone()],
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(account.key, program_id),
+            &[account.clone(), system_prog.clone()],
+            &[signer_seeds],
+        )?;
+    }
+    Ok(())
 }
 
-pub fn set_bet_ids(mut bet_account: &mut BetAcc, instruction_data: &[u8]) {
-    bet_account.sport = instruction_data[0];
-    bet_account.league = bytes_to_num(instruction_data, 1, 5) as u32;
-    bet_account.event = bytes_to_num(instruction_data, 5, 13);
-    bet_account.period = instruction_data[13];
-    bet_account.mkt = bytes_to_num(instruction_data, 14, 16) as u16;
-    bet_account.player = bytes_to_num(instruction_data, 16, 20) as u32;
+// close a program owned account once it is settled: refund its lamports, wipe and shrink its data and
+// hand it back to the system program. the runtime only lets an account with zeroed data change owner,
+// so an empty system owned account is the closed marker, anything sending lamports to it later in the
+// same tx revives a blank system account that fails every owner and account type check
+pub fn close_account(account: &AccountInfo, refund_to: &AccountInfo) -> ProgramResult {
+    math::drain_lamports(account, refund_to)?;
+    account.data.borrow_mut().fill(0);
+    account.realloc(0, false)?;
+    account.assign(&system_program::id());
+    Ok(())
 }
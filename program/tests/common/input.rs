@@ -0,0 +1,55 @@
+// program input laid out the way the runtime serializes it, so realloc and assign find the memory
+// around each account they expect, read back with entrypoint::deserialize. shared by the decode
+// tests and the fuzz target through #[path] so both feed process_instruction the same way, and kept
+// out of src so it isn't part of the program's api
+use solana_program::{
+    entrypoint::{BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER},
+    pubkey::Pubkey,
+};
+
+pub enum InputAccount {
+    Account {
+        key: Pubkey,
+        owner: Pubkey,
+        is_signer: bool,
+        is_writable: bool,
+        lamports: u64,
+        data: Vec<u8>,
+    },
+    Dup(u8), // same account as an earlier index
+}
+
+pub fn serialize_input(program_id: &Pubkey, accounts: &[InputAccount], instruction_data: &[u8]) -> Vec<u64> {
+    let mut bytes = (accounts.len() as u64).to_le_bytes().to_vec();
+    for account in accounts {
+        match account {
+            InputAccount::Account {
+                key,
+                owner,
+                is_signer,
+                is_writable,
+                lamports,
+                data,
+            } => {
+                bytes.extend_from_slice(&[NON_DUP_MARKER, *is_signer as u8, *is_writable as u8, 0]);
+                bytes.extend_from_slice(&[0; 4]); // original data len, written by deserialize
+                bytes.extend_from_slice(key.as_ref());
+                bytes.extend_from_slice(owner.as_ref());
+                bytes.extend_from_slice(&lamports.to_le_bytes());
+                bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+                bytes.extend_from_slice(data);
+                bytes.extend_from_slice(&[0; MAX_PERMITTED_DATA_INCREASE]);
+                bytes.resize(bytes.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+                bytes.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+            }
+            InputAccount::Dup(index) => bytes.extend_from_slice(&[*index, 0, 0, 0, 0, 0, 0, 0]),
+        }
+    }
+    bytes.extend_from_slice(&(instruction_data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(instruction_data);
+    bytes.extend_from_slice(program_id.as_ref());
+    // u64 words keep the buffer 8 byte aligned like the runtime's
+    let mut words = vec![0u64; bytes.len().div_ceil(8)];
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), words.as_mut_ptr() as *mut u8, bytes.len()) };
+    words
+}
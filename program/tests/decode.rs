@@ -0,0 +1,411 @@
+// instruction decoding and a deterministic stand in for the fuzz target in fuzz/,
+// kept out of purebet.rs since program-test replaces the syscall stubs for the whole binary
+#[path = "common/input.rs"]
+mod input;
+
+use borsh::BorshSerialize;
+use input::{serialize_input, InputAccount};
+use pbv2::{
+    client,
+    error::PurebetError,
+    instruction::{BetIds, ConfigArgs, PurebetInstruction},
+    market::{MarketKind, PlayerCode},
+    utils::{self, BetAcc, Config, Market, MarketResult, MarketStatus, Pool, ProgramAccount, Score},
+};
+use solana_program::{
+    entrypoint,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+const IDS: BetIds = BetIds {
+    sport: 1,
+    league: 487,
+    event: 1_555_000,
+    period: 0,
+    mkt: 0,
+    player: 0,
+};
+
+fn valid_instructions() -> Vec<PurebetInstruction> {
+    let args = ConfigArgs {
+        admin: [9; 32],
+        market_makers: [[8; 32]; utils::MAX_MARKET_MAKERS],
+        odds_tolerance_bps: 100,
+    };
+    vec![
+        PurebetInstruction::InitBet {
+            ids: IDS,
+            stake0: 100,
+            stake1: 200,
+            side: 0,
+            to_aggregate: true,
+            nonce: 3,
+        },
+        PurebetInstruction::Take {
+            ids: IDS,
+            stake0: 100,
+            stake1: 200,
+            side: 1,
+        },
+        PurebetInstruction::PartialTake {
+            ids: IDS,
+            stake0: 50,
+            stake1: 100,
+            side: 1,
+            nonce: 4,
+            max_slippage_bps: 50,
+        },
+        PurebetInstruction::Cancel { ids: IDS, side: 0 },
+        PurebetInstruction::Refund { ids: IDS, side: 1 },
+        PurebetInstruction::SetDelay { seconds: 30 },
+        PurebetInstruction::Grade { ids: IDS, winner: 1 },
+        PurebetInstruction::Push { ids: IDS },
+        PurebetInstruction::InitConfig { args: args.clone() },
+        PurebetInstruction::UpdateConfig { args },
+        PurebetInstruction::SetPool { bump: 255 },
+        PurebetInstruction::MigrateBet,
+        PurebetInstruction::InitVault,
+        PurebetInstruction::InitMarket {
+            ids: IDS,
+            start_time: 1_700_000_000,
+        },
+        PurebetInstruction::UpdateMarket {
+            ids: IDS,
+            status: MarketStatus::Suspended,
+            start_time: 1_700_000_000,
+        },
+        PurebetInstruction::MigrateConfig,
+        PurebetInstruction::SetOracle { oracle: [9; 32] },
+        PurebetInstruction::PostResult {
+            ids: IDS,
+            result: MarketResult::Side0Won,
+        },
+        PurebetInstruction::Settle { ids: IDS },
+        PurebetInstruction::PostScore {
+            event: IDS.event_ids(),
+            home: 3,
+            away: 1,
+        },
+        PurebetInstruction::GradeMarket { ids: IDS },
+    ]
+}
+
+fn custom(error: PurebetError) -> ProgramError {
+    ProgramError::Custom(error as u32)
+}
+
+#[test]
+fn valid_instructions_round_trip() {
+    for instruction in valid_instructions() {
+        let data = instruction.try_to_vec().unwrap();
+        assert_eq!(PurebetInstruction::unpack(&data).unwrap(), instruction);
+
+        // every truncation and any trailing byte is rejected
+        for len in 0..data.len() {
+            assert_eq!(
+                PurebetInstruction::unpack(&data[..len]).unwrap_err(),
+                custom(PurebetError::InvalidInstruction)
+            );
+        }
+        let mut long = data.clone();
+        long.push(0);
+        assert_eq!(
+            PurebetInstruction::unpack(&long).unwrap_err(),
+            custom(PurebetError::InvalidInstruction)
+        );
+    }
+}
+
+#[test]
+fn out_of_range_fields_are_rejected() {
+    let sides = [
+        PurebetInstruction::InitBet {
+            ids: IDS,
+            stake0: 100,
+            stake1: 200,
+            side: 2,
+            to_aggregate: false,
+            nonce: 0,
+        },
+        PurebetInstruction::Take {
+            ids: IDS,
+            stake0: 100,
+            stake1: 200,
+            side: 2,
+        },
+        PurebetInstruction::Cancel { ids: IDS, side: u8::MAX },
+        PurebetInstruction::Grade { ids: IDS, winner: 2 },
+    ];
+    for instruction in sides {
+        let data = instruction.try_to_vec().unwrap();
+        assert_eq!(PurebetInstruction::unpack(&data).unwrap_err(), custom(PurebetError::InvalidSide));
+    }
+
+    let slippage = PurebetInstruction::PartialTake {
+        ids: IDS,
+        stake0: 50,
+        stake1: 100,
+        side: 1,
+        nonce: 0,
+        max_slippage_bps: 10_001,
+    };
+    let data = slippage.try_to_vec().unwrap();
+    assert_eq!(
+        PurebetInstruction::unpack(&data).unwrap_err(),
+        custom(PurebetError::InvalidOddsTolerance)
+    );
+
+    // to_aggregate is the second to last byte of InitBet, only 0 and 1 are bools
+    let mut data = valid_instructions()[0].try_to_vec().unwrap();
+    let to_aggregate = data.len() - 9;
+    data[to_aggregate] = 2;
+    assert_eq!(
+        PurebetInstruction::unpack(&data).unwrap_err(),
+        custom(PurebetError::InvalidInstruction)
+    );
+}
+
+#[test]
+fn pool_authority_reports_bumps_that_dont_derive_a_pda() {
+    let mint = Pubkey::new_from_array([1; 32]);
+    let (authority, bump) = utils::find_pool_authority(&mint, &PROGRAM_ID);
+    let pool = Pool { mint: mint.to_bytes(), vault: [2; 32], bump };
+    assert_eq!(client::pool_authority(&PROGRAM_ID, &pool), Ok(authority));
+
+    // about half of all bumps land on the curve, a config read off chain could hold one
+    let bad = (0..=u8::MAX)
+        .map(|bump| Pool { bump, ..pool })
+        .find(|pool| Pubkey::create_program_address(&[b"pool", &pool.mint, &[pool.bump]], &PROGRAM_ID).is_err())
+        .unwrap();
+    assert!(client::pool_authority(&PROGRAM_ID, &bad).is_err());
+    let ix = client::settle(&PROGRAM_ID, &bad, &spl_token::id(), &Pubkey::new_unique(), &Pubkey::new_unique(), &Pubkey::new_unique(), &Pubkey::new_unique(), IDS);
+    assert!(ix.is_err());
+}
+
+#[test]
+fn market_and_player_codes_round_trip() {
+    let kinds = [
+        (0, MarketKind::Moneyline),
+        (1, MarketKind::Home),
+        (2, MarketKind::Away),
+        (3, MarketKind::Draw),
+        (4, MarketKind::Spread(-196)),
+        (196, MarketKind::Spread(-4)),
+        (200, MarketKind::Spread(0)),
+        (203, MarketKind::Spread(3)),
+        (396, MarketKind::Spread(196)),
+        (400, MarketKind::AsianSpread(-199)),
+        (499, MarketKind::AsianSpread(-1)),
+        (500, MarketKind::AsianSpread(1)),
+        (501, MarketKind::AsianSpread(3)),
+        (599, MarketKind::AsianSpread(199)),
+        (600, MarketKind::AsianTotal(1)),
+        (605, MarketKind::AsianTotal(11)),
+        (999, MarketKind::AsianTotal(799)),
+        (1000, MarketKind::Total(0)),
+        (1095, MarketKind::Total(95)),
+        (u16::MAX, MarketKind::Total(u16::MAX - 1000)),
+    ];
+    for (mkt, kind) in kinds {
+        assert_eq!(MarketKind::decode(mkt), Some(kind));
+        assert_eq!(kind.encode(), Some(mkt));
+    }
+    // every code decodes to the kind that encodes back to it, the gap between spreads and asian spreads is unused
+    for mkt in 0..=u16::MAX {
+        match MarketKind::decode(mkt) {
+            Some(kind) => assert_eq!(kind.encode(), Some(mkt)),
+            None => assert!((397..400).contains(&mkt)),
+        }
+    }
+    let unencodable = [
+        MarketKind::Spread(197),
+        MarketKind::Total(u16::MAX),
+        MarketKind::AsianSpread(2), // half point lines are spreads
+        MarketKind::AsianSpread(201),
+        MarketKind::AsianSpread(-201),
+        MarketKind::AsianTotal(0),
+        MarketKind::AsianTotal(801),
+    ];
+    for kind in unencodable {
+        assert_eq!(kind.encode(), None);
+    }
+
+    let players = [
+        ("LeBron", "James", [b'J', b'A', b'M'], 0),
+        ("Yao", "Ming", [b'M', b'I', b'N'], 0),
+        ("Kevin", "de Bruyne", [b'D', b'E', b'B'], 1),
+        ("Pau", "Ng", [b'N', b'G', 0], 0),
+        ("Wu", "X", [b'X', 0, 0], 0),
+        ("Shaquille", "O'Neal", [b'O', b'N', b'E'], 0),
+        ("Alex", "Oxlade-Chamberlain", [b'O', b'X', b'L'], 0),
+        ("Rafael", "van der Vaart", [b'V', b'A', b'N'], 2),
+    ];
+    for (first, last, last_name, spaces) in players {
+        let player = PlayerCode::new(first, last).unwrap();
+        assert_eq!(player.first_initial, first.as_bytes()[0].to_ascii_uppercase());
+        assert_eq!((player.last_name, player.spaces), (last_name, spaces));
+        let code = player.encode().unwrap();
+        assert_ne!(code, 0);
+        assert_eq!(PlayerCode::decode(code), Some(player));
+    }
+    assert_eq!(PlayerCode::new("", "James"), None);
+    assert_eq!(PlayerCode::new("LeBron", "'"), None);
+
+    // one byte per letter from the low byte up, the space count in the top bits
+    assert_eq!(PlayerCode::new("LeBron", "James").unwrap().encode(), Some(u32::from_le_bytes(*b"LJAM")));
+    assert_eq!(
+        PlayerCode::new("Rafael", "van der Vaart").unwrap().encode(),
+        Some(u32::from_le_bytes([b'R', b'V' | 0x80, b'A', b'N']))
+    );
+
+    // 0 is no player, lowercase or non letters, no last name and a letter after the end of the name don't decode
+    let bad = [
+        0,
+        u32::from_le_bytes(*b"LjAM"),
+        u32::from_le_bytes(*b"L'AM"),
+        u32::from_le_bytes([b'L', 0, 0, 0]),
+        u32::from_le_bytes([b'L', b'N', 0, b'G']),
+        u32::from_le_bytes([0x80, 0, 0, 0]),
+    ];
+    for code in bad {
+        assert_eq!(PlayerCode::decode(code), None);
+    }
+}
+
+// xorshift, so the inputs are the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+#[test]
+fn process_instruction_never_panics() {
+    let config_key = utils::find_config_address(&PROGRAM_ID).0;
+    let keys = [
+        config_key,
+        spl_token::id(),
+        spl_token_2022::id(),
+        system_program::id(),
+        Pubkey::new_from_array([1; 32]),
+        Pubkey::new_from_array([2; 32]),
+        Pubkey::new_from_array([8; 32]),
+        Pubkey::new_from_array([9; 32]),
+    ];
+    let owners = [PROGRAM_ID, spl_token::id(), spl_token_2022::id(), system_program::id()];
+
+    // well formed program accounts get handlers past their first checks
+    let config = Config {
+        is_initialized: true,
+        admin: [9; 32],
+        market_makers: [[8; 32]; utils::MAX_MARKET_MAKERS],
+        oracle: [9; 32],
+        ..Default::default()
+    };
+    let bet = BetAcc {
+        sport: IDS.sport,
+        league: IDS.league,
+        event: IDS.event,
+        stake0: 100,
+        stake1: 200,
+        wallet0: [1; 32],
+        rent_payer: [1; 32],
+        ..Default::default()
+    };
+    let mut config_data = vec![0; utils::CONFIG_LEN];
+    config.pack(&mut config_data).unwrap();
+    let mut bet_data = vec![0; utils::BET_ACC_LEN];
+    bet.pack(&mut bet_data).unwrap();
+    let market = Market {
+        sport: IDS.sport,
+        league: IDS.league,
+        event: IDS.event,
+        start_time: u64::MAX,
+        ..Default::default()
+    };
+    let mut market_data = vec![0; utils::MARKET_LEN];
+    market.pack(&mut market_data).unwrap();
+    let score = Score {
+        sport: IDS.sport,
+        league: IDS.league,
+        event: IDS.event,
+        home: 3,
+        away: 1,
+        ..Default::default()
+    };
+    let mut score_data = vec![0; utils::SCORE_LEN];
+    score.pack(&mut score_data).unwrap();
+    let templates = [
+        config_data,
+        bet_data,
+        market_data,
+        score_data,
+        vec![2, 1, 1, 30],
+        vec![0; utils::CANCEL_DELAY_LEN],
+    ];
+    let instructions: Vec<Vec<u8>> = valid_instructions().iter().map(|ix| ix.try_to_vec().unwrap()).collect();
+
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        let mut instruction_data = match rng.below(3) {
+            0 => {
+                let len = rng.below(80);
+                rng.bytes(len)
+            }
+            _ => instructions[rng.below(instructions.len())].clone(),
+        };
+        // flip a few bytes of the valid encodings
+        for _ in 0..rng.below(4) {
+            if !instruction_data.is_empty() {
+                let i = rng.below(instruction_data.len());
+                instruction_data[i] = rng.next() as u8;
+            }
+        }
+
+        let count = rng.below(14);
+        let accounts: Vec<InputAccount> = (0..count)
+            .map(|i| {
+                if i > 0 && rng.below(8) == 0 {
+                    return InputAccount::Dup(rng.below(i) as u8);
+                }
+                let data = match rng.below(3) {
+                    0 => {
+                        let len = rng.below(200);
+                        rng.bytes(len)
+                    }
+                    _ => templates[rng.below(templates.len())].clone(),
+                };
+                let flags = rng.next();
+                InputAccount::Account {
+                    key: keys[rng.below(keys.len())],
+                    owner: owners[rng.below(owners.len())],
+                    is_signer: flags & 1 == 1,
+                    is_writable: flags & 2 == 2,
+                    lamports: rng.next(),
+                    data,
+                }
+            })
+            .collect();
+
+        let mut input = serialize_input(&PROGRAM_ID, &accounts, &instruction_data);
+        let (program_id, infos, instruction_data) = unsafe { entrypoint::deserialize(input.as_mut_ptr() as *mut u8) };
+        let _ = pbv2::process_instruction(program_id, &infos, instruction_data);
+    }
+}
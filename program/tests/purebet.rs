@@ -0,0 +1,2172 @@
+// end to end tests against a local bank, the program runs natively through processor!
+use pbv2::{
+    client,
+    error::PurebetError,
+    instruction::{BetIds, ConfigArgs, EventIds},
+    market::MarketKind,
+    utils::{self, BetAcc, BetAccV0, BetAccV1, BetAccV2, CancelDelay, Config, ConfigV1, Market, MarketResult, MarketStatus, Pool, ProgramAccount},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey, system_instruction, system_program};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_2022::{
+    extension::{transfer_fee, ExtensionType, StateWithExtensions},
+    state::{Account as TokenAccount, Mint},
+};
+
+const FUNDS: u64 = 1_000_000;
+
+const IDS: BetIds = BetIds {
+    sport: 1,
+    league: 487,
+    event: 1_555_000,
+    period: 0,
+    mkt: 0,
+    player: 0,
+};
+
+struct Env {
+    ctx: ProgramTestContext,
+    program_id: Pubkey,
+    token_program: Pubkey,
+    pool: Pool,
+    admin: Keypair,
+    oracle: Keypair,
+    maker: Keypair,
+    taker: Keypair,
+    maker_tok: Pubkey,
+    taker_tok: Pubkey,
+    delay_acc: Pubkey,
+}
+
+// config and delay acc are preloaded since InitConfig needs the admin key compiled into the program
+// and nothing creates delay accounts, fee_bps adds a token-2022 transfer fee to the mint
+async fn setup(token_program: Pubkey, fee_bps: Option<u16>) -> Env {
+    let program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("pbv2", program_id, processor!(pbv2::process_instruction));
+
+    let admin = Keypair::new();
+    let maker = Keypair::new();
+    let taker = Keypair::new();
+    let oracle = Keypair::new();
+    for user in [&admin, &oracle, &maker, &taker] {
+        program_test.add_account(
+            user.pubkey(),
+            Account::new(10_000_000_000, 0, &system_program::id()),
+        );
+    }
+
+    let config = Config {
+        is_initialized: true,
+        admin: admin.pubkey().to_bytes(),
+        odds_tolerance_bps: 100,
+        ..Default::default()
+    };
+    let mut config_data = vec![0; utils::CONFIG_LEN];
+    config.pack(&mut config_data).unwrap();
+    program_test.add_account(
+        client::config_address(&program_id),
+        Account {
+            lamports: 1_000_000_000,
+            data: config_data,
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+
+    // created empty by the admin, set_delay writes its header
+    let delay_acc = Pubkey::new_unique();
+    program_test.add_account(
+        delay_acc,
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; utils::CANCEL_DELAY_LEN],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+
+    let mut ctx = program_test.start_with_context().await;
+
+    let mint = Keypair::new();
+    let mint_extensions: Vec<ExtensionType> = fee_bps.iter().map(|_| ExtensionType::TransferFeeConfig).collect();
+    let account_extensions: Vec<ExtensionType> = fee_bps.iter().map(|_| ExtensionType::TransferFeeAmount).collect();
+    let mint_len = ExtensionType::try_calculate_account_len::<Mint>(&mint_extensions).unwrap();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let payer = ctx.payer.pubkey();
+
+    let mut ixs = vec![system_instruction::create_account(
+        &payer,
+        &mint.pubkey(),
+        rent.minimum_balance(mint_len),
+        mint_len as u64,
+        &token_program,
+    )];
+    if let Some(fee_bps) = fee_bps {
+        ixs.push(
+            transfer_fee::instruction::initialize_transfer_fee_config(
+                &token_program,
+                &mint.pubkey(),
+                None,
+                None,
+                fee_bps,
+                u64::MAX,
+            )
+            .unwrap(),
+        );
+    }
+    ixs.push(spl_token_2022::instruction::initialize_mint2(&token_program, &mint.pubkey(), &payer, None, 6).unwrap());
+    process(&mut ctx, &ixs, &[&mint]).await.unwrap();
+
+    let maker_tok = create_token_account(&mut ctx, &token_program, &mint.pubkey(), &maker.pubkey(), &account_extensions).await;
+    let taker_tok = create_token_account(&mut ctx, &token_program, &mint.pubkey(), &taker.pubkey(), &account_extensions).await;
+    for tok in [maker_tok, taker_tok] {
+        let ix = spl_token_2022::instruction::mint_to(&token_program, &mint.pubkey(), &tok, &payer, &[], FUNDS).unwrap();
+        process(&mut ctx, &[ix], &[]).await.unwrap();
+    }
+
+    let ix = client::init_vault(&program_id, &admin.pubkey(), &mint.pubkey(), &token_program);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+    let (_, bump) = utils::find_pool_authority(&mint.pubkey(), &program_id);
+    let vault = client::vault_address(&program_id, &mint.pubkey());
+
+    let clock: Clock = ctx.banks_client.get_sysvar().await.unwrap();
+    let start_time = clock.unix_timestamp as u64 + 86_400;
+    let ix = client::init_market(&program_id, &admin.pubkey(), IDS, start_time);
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+
+    let ix = client::set_oracle(&program_id, &admin.pubkey(), &oracle.pubkey());
+    process(&mut ctx, &[ix], &[&admin]).await.unwrap();
+
+    Env {
+        ctx,
+        program_id,
+        token_program,
+        pool: Pool {
+            mint: mint.pubkey().to_bytes(),
+            vault: vault.to_bytes(),
+            bump,
+        },
+        admin,
+        oracle,
+        maker,
+        taker,
+        maker_tok,
+        taker_tok,
+        delay_acc,
+    }
+}
+
+async fn create_token_account(
+    ctx: &mut ProgramTestContext,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    extensions: &[ExtensionType],
+) -> Pubkey {
+    let account = Keypair::new();
+    let len = ExtensionType::try_calculate_account_len::<TokenAccount>(extensions).unwrap();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let ixs = [
+        system_instruction::create_account(
+            &ctx.payer.pubkey(),
+            &account.pubkey(),
+            rent.minimum_balance(len),
+            len as u64,
+            token_program,
+        ),
+        spl_token_2022::instruction::initialize_account3(token_program, &account.pubkey(), mint, owner).unwrap(),
+    ];
+    process(ctx, &ixs, &[&account]).await.unwrap();
+    account.pubkey()
+}
+
+// ctx.payer pays the fees so other signers' lamports only move by rent
+async fn process(ctx: &mut ProgramTestContext, ixs: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+    let blockhash = ctx.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&ctx.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(ixs, Some(&ctx.payer.pubkey()), &all_signers, blockhash);
+    ctx.banks_client.process_transaction(tx).await
+}
+
+fn assert_purebet_error(result: Result<(), BanksClientError>, error: PurebetError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+    );
+}
+
+impl Env {
+    async fn balance(&mut self, tok: Pubkey) -> u64 {
+        let account = self.ctx.banks_client.get_account(tok).await.unwrap().unwrap();
+        StateWithExtensions::<TokenAccount>::unpack(&account.data).unwrap().base.amount
+    }
+
+    async fn lamports(&mut self, key: Pubkey) -> u64 {
+        self.ctx.banks_client.get_balance(key).await.unwrap()
+    }
+
+    async fn bet_acc(&mut self, bet: Pubkey) -> Option<BetAcc> {
+        let account = self.ctx.banks_client.get_account(bet).await.unwrap()?;
+        Some(BetAcc::unpack(&account.data).unwrap())
+    }
+
+    fn vault(&self) -> Pubkey {
+        Pubkey::new_from_array(self.pool.vault)
+    }
+
+    // maker bets stake0 on side 0 at 3.0, leaving stake1 open for a taker
+    async fn make(&mut self, stake0: u64, stake1: u64, to_aggregate: bool, nonce: u64) -> Pubkey {
+        let maker = self.maker.pubkey();
+        let ix = client::maker(
+            &self.program_id,
+            &self.pool,
+            &self.token_program,
+            &self.maker_tok,
+            &maker,
+            &maker,
+            &maker,
+            IDS,
+            stake0,
+            stake1,
+            0,
+            to_aggregate,
+            nonce,
+        );
+        let maker_kp = self.maker.insecure_clone();
+        process(&mut self.ctx, &[ix], &[&maker_kp]).await.unwrap();
+        client::bet_address(&self.program_id, &IDS, &maker, nonce)
+    }
+
+    // taker matches the open stake1 of a bet from make
+    async fn take(&mut self, bet: Pubkey) {
+        let taker = self.taker.insecure_clone();
+        let ix = client::taker(
+            &self.program_id,
+            &self.pool,
+            &self.token_program,
+            &bet,
+            &self.taker_tok,
+            &taker.pubkey(),
+            IDS,
+            100,
+            200,
+            1,
+        );
+        process(&mut self.ctx, &[ix], &[&taker]).await.unwrap();
+    }
+
+    async fn post_result(&mut self, signer: &Keypair, result: MarketResult) -> Result<(), BanksClientError> {
+        let ix = client::post_result(&self.program_id, &signer.pubkey(), IDS, result);
+        process(&mut self.ctx, &[ix], &[signer]).await
+    }
+
+    // oracle posts the score of the event and the moneyline in IDS is graded from it
+    async fn grade_from_score(&mut self, home: u32, away: u32) {
+        let oracle = self.oracle.insecure_clone();
+        let score = client::post_score(&self.program_id, &oracle.pubkey(), IDS.event_ids(), home, away);
+        let grade = client::grade_market(&self.program_id, IDS);
+        process(&mut self.ctx, &[score, grade], &[&oracle]).await.unwrap();
+    }
+
+    // only ctx.payer signs, settling needs nobody's permission
+    async fn settle(&mut self, bet: Pubkey, destination0: Pubkey, destination1: Pubkey) -> Result<(), BanksClientError> {
+        let ix = client::settle(
+            &self.program_id,
+            &self.pool,
+            &self.token_program,
+            &bet,
+            &destination0,
+            &destination1,
+            &self.maker.pubkey(),
+            IDS,
+        ).unwrap();
+        process(&mut self.ctx, &[ix], &[]).await
+    }
+
+    async fn cancel(&mut self, bet: Pubkey, signer: &Keypair, is_refund: bool, delay: bool) -> Result<(), BanksClientError> {
+        let delay_acc = if delay { Some(&self.delay_acc) } else { None };
+        let build = if is_refund { client::refund } else { client::cancel };
+        let ix = build(
+            &self.program_id,
+            &self.pool,
+            &self.token_program,
+            &bet,
+            &self.maker_tok,
+            &signer.pubkey(),
+            &self.maker.pubkey(),
+            delay_acc,
+            IDS,
+            0,
+        )
+        .unwrap();
+        process(&mut self.ctx, &[ix], &[signer]).await
+    }
+
+    // rewrite a bet's state directly, for values that can't be reached through instructions
+    async fn overwrite_bet(&mut self, bet: Pubkey, bet_acc: &BetAcc) {
+        let mut account = self.ctx.banks_client.get_account(bet).await.unwrap().unwrap();
+        bet_acc.pack(&mut account.data).unwrap();
+        self.ctx.set_account(&bet, &AccountSharedData::from(account));
+    }
+
+    // let the taker match free bets and bets marked for aggregation
+    async fn add_taker_as_market_maker(&mut self) {
+        let config_key = client::config_address(&self.program_id);
+        let account = self.ctx.banks_client.get_account(config_key).await.unwrap().unwrap();
+        let mut market_makers = Config::unpack(&account.data).unwrap().market_makers;
+        market_makers[0] = self.taker.pubkey().to_bytes();
+        let admin = self.admin.insecure_clone();
+        let args = ConfigArgs {
+            admin: admin.pubkey().to_bytes(),
+            market_makers,
+            odds_tolerance_bps: 0,
+        };
+        let ix = client::update_config(&self.program_id, &admin.pubkey(), args);
+        process(&mut self.ctx, &[ix], &[&admin]).await.unwrap();
+    }
+
+    async fn set_delay(&mut self, signer: &Keypair, seconds: u8) -> Result<(), BanksClientError> {
+        let ix = client::set_delay(&self.program_id, &self.delay_acc, &signer.pubkey(), seconds);
+        process(&mut self.ctx, &[ix], &[signer]).await
+    }
+}
+
+#[tokio::test]
+async fn maker_then_taker() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!(bet_acc.wallet0, env.maker.pubkey().to_bytes());
+    assert!(utils::blank_wallet(bet_acc.wallet1));
+    assert_eq!((bet_acc.stake0, bet_acc.stake1), (100, 200));
+    assert_eq!(bet_acc.rent_payer, env.maker.pubkey().to_bytes());
+    assert_eq!(bet_acc.mint, env.pool.mint);
+    assert!(!bet_acc.is_free_bet);
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS - 100);
+    assert_eq!(env.balance(env.vault()).await, 100);
+
+    let taker = env.taker.insecure_clone();
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        100,
+        200,
+        1,
+    );
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!(bet_acc.wallet0, env.maker.pubkey().to_bytes());
+    assert_eq!(bet_acc.wallet1, taker.pubkey().to_bytes());
+    assert_eq!((bet_acc.stake0, bet_acc.stake1), (100, 200));
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS - 200);
+    assert_eq!(env.balance(env.vault()).await, 300);
+
+    // a matched bet can't be taken again
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        100,
+        200,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::InvalidBetState);
+}
+
+#[tokio::test]
+async fn taker_asking_for_better_odds_fails() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+
+    let taker = env.taker.insecure_clone();
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        110,
+        200,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::OddsTooHigh);
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn taker_is_filled_at_maker_price() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    let taker = env.taker.insecure_clone();
+
+    // 150 doesn't cover the 200 open on side 1
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        50,
+        150,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::InsufficientStake);
+
+    // willing to risk up to 250 to win 100, the maker's 200 to win 100 is better so only 200 moves
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        100,
+        250,
+        1,
+    );
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!(bet_acc.wallet1, taker.pubkey().to_bytes());
+    assert_eq!((bet_acc.stake0, bet_acc.stake1), (100, 200));
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS - 200);
+    assert_eq!(env.balance(env.vault()).await, 300);
+}
+
+// indexes into an instruction's accounts, each constraint is broken on its own and has to fail
+// with its own error in parse or check_mint
+struct Constraints {
+    signers: &'static [(usize, PurebetError)],
+    written: &'static [usize],
+    mint: usize,
+    token_accounts: &'static [usize],
+}
+
+// copy of a token account at a new address, holding another mint
+async fn wrong_mint_token_account(env: &mut Env, tok: Pubkey) -> Pubkey {
+    let mut account = env.ctx.banks_client.get_account(tok).await.unwrap().unwrap();
+    let mut state = spl_token::state::Account::unpack(&account.data).unwrap();
+    state.mint = Pubkey::new_unique();
+    spl_token::state::Account::pack(state, &mut account.data).unwrap();
+    let copy = Pubkey::new_unique();
+    env.ctx.set_account(&copy, &AccountSharedData::from(account));
+    copy
+}
+
+async fn assert_constraints_checked(env: &mut Env, ix: &Instruction, keypairs: &[&Keypair], constraints: Constraints) {
+    let mut broken = vec![];
+    // a signer swapped for a key that doesn't sign
+    for &(index, error) in constraints.signers {
+        let mut ix = ix.clone();
+        ix.accounts[index].pubkey = Pubkey::new_unique();
+        ix.accounts[index].is_signer = false;
+        broken.push((ix, error));
+    }
+    for &index in constraints.written {
+        let mut ix = ix.clone();
+        ix.accounts[index].is_writable = false;
+        broken.push((ix, PurebetError::AccountNotWritable));
+    }
+    // the bet passed again in each other written slot, a signer's key goes into the token source
+    // instead, so the duplicate is reported rather than the missing signature or the bet's owner
+    let (bet, source) = (constraints.written[0], constraints.written[1]);
+    for &index in &constraints.written[1..] {
+        let mut ix = ix.clone();
+        if ix.accounts[index].is_signer {
+            ix.accounts[source].pubkey = ix.accounts[index].pubkey;
+        } else {
+            ix.accounts[index].pubkey = ix.accounts[bet].pubkey;
+        }
+        broken.push((ix, PurebetError::DuplicateAccount));
+    }
+    let mut wrong_mint = ix.clone();
+    wrong_mint.accounts[constraints.mint].pubkey = Pubkey::new_unique();
+    broken.push((wrong_mint, PurebetError::MintMismatch));
+    for &index in constraints.token_accounts {
+        let mut ix = ix.clone();
+        ix.accounts[index].pubkey = wrong_mint_token_account(env, ix.accounts[index].pubkey).await;
+        broken.push((ix, PurebetError::MintMismatch));
+    }
+
+    for (ix, error) in broken {
+        let signers: Vec<&Keypair> = keypairs
+            .iter()
+            .copied()
+            .filter(|keypair| ix.accounts.iter().any(|meta| meta.is_signer && meta.pubkey == keypair.pubkey()))
+            .collect();
+        let result = process(&mut env.ctx, &[ix], &signers).await;
+        assert_purebet_error(result, error);
+    }
+}
+
+#[tokio::test]
+async fn account_constraints_are_checked_before_anything_moves() {
+    let mut env = setup(spl_token::id(), None).await;
+    let maker = env.maker.insecure_clone();
+    let taker = env.taker.insecure_clone();
+
+    let make = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        IDS,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    let constraints = Constraints {
+        signers: &[(4, PurebetError::BettorNotSigner), (6, PurebetError::RentPayerNotSigner)],
+        written: &[0, 2, 3, 6],
+        mint: 9,
+        token_accounts: &[2],
+    };
+    assert_constraints_checked(&mut env, &make, &[&maker], constraints).await;
+    let mut ix = make.clone();
+    ix.accounts[7].pubkey = spl_token::id();
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::IncorrectSystemProgram);
+    process(&mut env.ctx, &[make], &[&maker]).await.unwrap();
+    let bet = client::bet_address(&env.program_id, &IDS, &maker.pubkey(), 0);
+
+    let partial = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        50,
+        100,
+        1,
+        7,
+        0,
+    ).unwrap();
+    let constraints = Constraints {
+        signers: &[(4, PurebetError::BettorNotSigner), (5, PurebetError::RentPayerNotSigner)],
+        written: &[0, 2, 3, 5, 6, 10],
+        mint: 9,
+        token_accounts: &[2, 10],
+    };
+    assert_constraints_checked(&mut env, &partial, &[&taker], constraints).await;
+    process(&mut env.ctx, &[partial], &[&taker]).await.unwrap();
+
+    let take = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        50,
+        100,
+        1,
+    );
+    let constraints = Constraints {
+        signers: &[(4, PurebetError::BettorNotSigner)],
+        written: &[0, 2, 3],
+        mint: 6,
+        token_accounts: &[2],
+    };
+    assert_constraints_checked(&mut env, &take, &[&taker], constraints).await;
+
+    // nothing moved on any of the rejected instructions
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS - 100);
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS - 100);
+    assert_eq!(env.balance(env.vault()).await, 200);
+    process(&mut env.ctx, &[take], &[&taker]).await.unwrap();
+    assert_eq!(env.balance(env.vault()).await, 300);
+}
+
+#[tokio::test]
+async fn market_status_and_start_time_gate_orders_and_matches() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    let admin = env.admin.insecure_clone();
+    let maker = env.maker.insecure_clone();
+    let taker = env.taker.insecure_clone();
+    let market = client::market_address(&env.program_id, &IDS);
+    let account = env.ctx.banks_client.get_account(market).await.unwrap().unwrap();
+    let market_acc = Market::unpack(&account.data).unwrap();
+    assert_eq!(market_acc.ids(), IDS);
+    assert_eq!(market_acc.status, MarketStatus::Open);
+    let start_time = market_acc.start_time;
+
+    let make = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        IDS,
+        100,
+        200,
+        0,
+        false,
+        1,
+    );
+    let take = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        100,
+        200,
+        1,
+    );
+
+    // only the admin manages markets
+    let ix = client::update_market(&env.program_id, &maker.pubkey(), IDS, MarketStatus::Suspended, start_time);
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::NotAdmin);
+
+    for status in [MarketStatus::Suspended, MarketStatus::Closed] {
+        let ix = client::update_market(&env.program_id, &admin.pubkey(), IDS, status, start_time);
+        process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+        let result = process(&mut env.ctx, std::slice::from_ref(&make), &[&maker]).await;
+        assert_purebet_error(result, PurebetError::MarketNotOpen);
+        let result = process(&mut env.ctx, std::slice::from_ref(&take), &[&taker]).await;
+        assert_purebet_error(result, PurebetError::MarketNotOpen);
+    }
+
+    // reopened, but the start time has passed
+    let clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    let ix = client::update_market(&env.program_id, &admin.pubkey(), IDS, MarketStatus::Open, clock.unix_timestamp as u64);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    let result = process(&mut env.ctx, std::slice::from_ref(&take), &[&taker]).await;
+    assert_purebet_error(result, PurebetError::MarketStarted);
+
+    // settling isn't a status the admin can set directly
+    let ix = client::update_market(&env.program_id, &admin.pubkey(), IDS, MarketStatus::Settled, start_time);
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::InvalidMarketStatus);
+
+    // a market can't be created twice
+    let ix = client::init_market(&env.program_id, &admin.pubkey(), IDS, start_time);
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::MarketAlreadyInitialized);
+
+    let ix = client::update_market(&env.program_id, &admin.pubkey(), IDS, MarketStatus::Open, start_time);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    process(&mut env.ctx, &[take], &[&taker]).await.unwrap();
+
+    // bets on ids without a market can't be placed
+    let other = BetIds { mkt: 1, ..IDS };
+    let ix = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        other,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::IncorrectOwner);
+}
+
+#[tokio::test]
+async fn maker_then_partial_taker() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+
+    let taker = env.taker.insecure_clone();
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        50,
+        100,
+        1,
+        7,
+        0,
+    ).unwrap();
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+
+    // the matched half moves to a new bet keyed by the partial taker, the rest stays open
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!(bet_acc.wallet0, env.maker.pubkey().to_bytes());
+    assert!(utils::blank_wallet(bet_acc.wallet1));
+    assert_eq!((bet_acc.stake0, bet_acc.stake1), (50, 100));
+
+    let new_bet = client::bet_address(&env.program_id, &IDS, &taker.pubkey(), 7);
+    let new_bet_acc = env.bet_acc(new_bet).await.unwrap();
+    assert_eq!(new_bet_acc.wallet0, env.maker.pubkey().to_bytes());
+    assert_eq!(new_bet_acc.wallet1, taker.pubkey().to_bytes());
+    assert_eq!((new_bet_acc.stake0, new_bet_acc.stake1), (50, 100));
+    assert_eq!(new_bet_acc.rent_payer, taker.pubkey().to_bytes());
+    assert_eq!(new_bet_acc.mint, env.pool.mint);
+
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS - 100);
+    assert_eq!(env.balance(env.vault()).await, 200);
+}
+
+#[tokio::test]
+async fn partial_fill_keeps_maker_price_and_returns_dust() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 173, false, 0).await;
+
+    // asking for less than the maker's price fills at the maker's price
+    let taker = env.taker.insecure_clone();
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        20,
+        50,
+        1,
+        0,
+        0,
+    ).unwrap();
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+
+    // 50 * 100 / 173 = 28.9 and 123 * 100 / 173 = 71.1 both round down, the last unit goes back to the maker
+    let new_bet = client::bet_address(&env.program_id, &IDS, &taker.pubkey(), 0);
+    let new_bet_acc = env.bet_acc(new_bet).await.unwrap();
+    assert_eq!((new_bet_acc.stake0, new_bet_acc.stake1), (28, 50));
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!((bet_acc.stake0, bet_acc.stake1), (71, 123));
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS - 99);
+    assert_eq!(env.balance(env.vault()).await, 99 + 50);
+
+    // better odds than the maker's price are rejected
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        29,
+        50,
+        1,
+        1,
+        0,
+    ).unwrap();
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::OddsTooHigh);
+
+    // and so is taking more than is open
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        71,
+        124,
+        1,
+        1,
+        0,
+    ).unwrap();
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::FillTooLarge);
+}
+
+#[tokio::test]
+async fn partial_fill_needs_a_stake_and_an_open_side() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+
+    let taker = env.taker.insecure_clone();
+    let partial = |stake1: u64, nonce: u64| {
+        client::partial_taker(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &bet,
+            &env.taker_tok,
+            &taker.pubkey(),
+            &taker.pubkey(),
+            &env.maker_tok,
+            IDS,
+            0,
+            stake1,
+            1,
+            nonce,
+            0,
+        ).unwrap()
+    };
+    let zero = partial(0, 0);
+    let fill = partial(50, 1);
+    let result = process(&mut env.ctx, &[zero], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::ZeroStake);
+
+    // a bet with nothing open on the taker's side isn't a fill that is too large
+    let mut bet_acc = env.bet_acc(bet).await.unwrap();
+    bet_acc.stake1 = 0;
+    env.overwrite_bet(bet, &bet_acc).await;
+    let result = process(&mut env.ctx, &[fill], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::NoOpenStake);
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn maker_needs_both_stakes() {
+    let mut env = setup(spl_token::id(), None).await;
+    let maker = env.maker.insecure_clone();
+    for (nonce, (stake0, stake1)) in [(0, 200), (100, 0)].into_iter().enumerate() {
+        let ix = client::maker(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &env.maker_tok,
+            &maker.pubkey(),
+            &maker.pubkey(),
+            &maker.pubkey(),
+            IDS,
+            stake0,
+            stake1,
+            0,
+            false,
+            nonce as u64,
+        );
+        let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+        assert_purebet_error(result, PurebetError::ZeroStake);
+    }
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn take_of_a_bet_with_nothing_open_fails() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    // what partial takers leave of a bet they filled all of
+    let mut bet_acc = env.bet_acc(bet).await.unwrap();
+    bet_acc.stake0 = 0;
+    bet_acc.stake1 = 0;
+    env.overwrite_bet(bet, &bet_acc).await;
+
+    let taker = env.taker.insecure_clone();
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        100,
+        200,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::NoOpenStake);
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn partial_fill_slippage_is_capped_by_config() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(1000, 2000, false, 0).await;
+    let taker = env.taker.insecure_clone();
+
+    // asking to win 101 for 200 is 1% better than the maker's 100 for 200, 102 is 2% better
+    // which is more than the config's 100 bps even when the taker allows 500
+    for (counter, max_slippage_bps, nonce) in [(101, 99, 0), (102, 500, 1)] {
+        let ix = client::partial_taker(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &bet,
+            &env.taker_tok,
+            &taker.pubkey(),
+            &taker.pubkey(),
+            &env.maker_tok,
+            IDS,
+            counter,
+            200,
+            1,
+            nonce,
+            max_slippage_bps,
+        ).unwrap();
+        let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+        assert_purebet_error(result, PurebetError::OddsTooHigh);
+    }
+
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        101,
+        200,
+        1,
+        2,
+        100,
+    ).unwrap();
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+    let new_bet = client::bet_address(&env.program_id, &IDS, &taker.pubkey(), 2);
+    let new_bet_acc = env.bet_acc(new_bet).await.unwrap();
+    assert_eq!((new_bet_acc.stake0, new_bet_acc.stake1), (100, 200));
+}
+
+#[tokio::test]
+async fn cancel_returns_stake_and_rent() {
+    let mut env = setup(spl_token::id(), None).await;
+    let lamports_before = env.lamports(env.maker.pubkey()).await;
+    let bet = env.make(100, 200, false, 0).await;
+    assert!(env.lamports(env.maker.pubkey()).await < lamports_before);
+
+    // only the bettor can cancel
+    let taker = env.taker.insecure_clone();
+    let result = env.cancel(bet, &taker, false, false).await;
+    assert_purebet_error(result, PurebetError::NotBettor);
+
+    let maker = env.maker.insecure_clone();
+    env.cancel(bet, &maker, false, false).await.unwrap();
+    assert!(env.bet_acc(bet).await.is_none());
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+    assert_eq!(env.balance(env.vault()).await, 0);
+    assert_eq!(env.lamports(env.maker.pubkey()).await, lamports_before);
+}
+
+#[tokio::test]
+async fn closed_bet_revived_in_same_tx_is_blank() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    let maker = env.maker.insecure_clone();
+
+    // rent can't be sent anywhere but the bet's rent payer
+    let taker = env.taker.pubkey();
+    let ix = client::cancel(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &taker,
+        None,
+        IDS,
+        0,
+    ).unwrap();
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::WrongRentPayer);
+
+    let cancel = client::cancel(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        None,
+        IDS,
+        0,
+    ).unwrap();
+    let rent = env.ctx.banks_client.get_rent().await.unwrap().minimum_balance(0);
+    let revive = system_instruction::transfer(&maker.pubkey(), &bet, rent);
+    process(&mut env.ctx, &[cancel.clone(), revive], &[&maker]).await.unwrap();
+
+    let account = env.ctx.banks_client.get_account(bet).await.unwrap().unwrap();
+    assert_eq!(account.owner, system_program::id());
+    assert!(account.data.is_empty());
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+
+    // the blank account can't be canceled again
+    let result = process(&mut env.ctx, &[cancel], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::IncorrectOwner);
+}
+
+#[tokio::test]
+async fn refund_by_admin() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+
+    let maker = env.maker.insecure_clone();
+    let result = env.cancel(bet, &maker, true, false).await;
+    assert_purebet_error(result, PurebetError::NotAdmin);
+
+    let admin = env.admin.insecure_clone();
+    env.cancel(bet, &admin, true, false).await.unwrap();
+    assert!(env.bet_acc(bet).await.is_none());
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+    assert_eq!(env.balance(env.vault()).await, 0);
+}
+
+#[tokio::test]
+async fn grade_pays_winner_both_stakes_and_closes_bet() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    env.take(bet).await;
+
+    let admin = env.admin.insecure_clone();
+    let maker = env.maker.pubkey();
+    let maker_lamports = env.lamports(maker).await;
+    let bet_lamports = env.lamports(bet).await;
+    let grade = |destination: &Pubkey, winner: u8| {
+        client::grade(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &bet,
+            destination,
+            &admin.pubkey(),
+            &maker,
+            IDS,
+            winner,
+        ).unwrap()
+    };
+    let (early, wrong_side, ix) = (grade(&env.maker_tok, 1), grade(&env.maker_tok, 0), grade(&env.taker_tok, 1));
+
+    // the admin can only pay out the side the oracle's result names
+    let result = process(&mut env.ctx, &[early], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::MarketNotSettled);
+    env.grade_from_score(1, 2).await;
+    let result = process(&mut env.ctx, &[wrong_side], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::ResultMismatch);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS - 200 + 300);
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS - 100);
+    assert_eq!(env.balance(env.vault()).await, 0);
+    assert!(env.bet_acc(bet).await.is_none());
+    assert_eq!(env.lamports(maker).await, maker_lamports + bet_lamports);
+}
+
+#[tokio::test]
+async fn push_returns_each_side_its_stake() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    env.take(bet).await;
+
+    let admin = env.admin.insecure_clone();
+    let maker = env.maker.pubkey();
+    let maker_lamports = env.lamports(maker).await;
+    let bet_lamports = env.lamports(bet).await;
+    let push = |destination0: &Pubkey, destination1: &Pubkey| {
+        client::push(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &bet,
+            destination0,
+            destination1,
+            &admin.pubkey(),
+            &maker,
+            IDS,
+        ).unwrap()
+    };
+    let (early, ix) = (push(&env.taker_tok, &env.maker_tok), push(&env.maker_tok, &env.taker_tok));
+
+    // nothing is pushed until the oracle voids the market
+    let result = process(&mut env.ctx, &[early], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::MarketNotSettled);
+    let oracle = env.oracle.insecure_clone();
+    env.post_result(&oracle, MarketResult::Void).await.unwrap();
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS);
+    assert_eq!(env.balance(env.vault()).await, 0);
+    assert!(env.bet_acc(bet).await.is_none());
+    assert_eq!(env.lamports(maker).await, maker_lamports + bet_lamports);
+}
+
+#[tokio::test]
+async fn partially_filled_free_bet_refunds_its_funder_on_push() {
+    let mut env = setup(spl_token::id(), None).await;
+    env.add_taker_as_market_maker().await;
+
+    // the maker funds a free bet for a bettor who never signs or pays anything
+    let maker = env.maker.insecure_clone();
+    let bettor = Keypair::new();
+    let ix = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &bettor.pubkey(),
+        &maker.pubkey(),
+        IDS,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    process(&mut env.ctx, &[ix], &[&maker]).await.unwrap();
+    let bet = client::bet_address(&env.program_id, &IDS, &bettor.pubkey(), 0);
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert!(bet_acc.is_free_bet);
+    assert_eq!(bet_acc.free_bet_side, 0);
+    assert_eq!(bet_acc.free_bet_funder, maker.pubkey().to_bytes());
+
+    // the market maker matches half and pays the new bet's rent, the funder stays on the new bet
+    let taker = env.taker.insecure_clone();
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        50,
+        100,
+        1,
+        0,
+        0,
+    ).unwrap();
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+    let new_bet = client::bet_address(&env.program_id, &IDS, &taker.pubkey(), 0);
+    let new_bet_acc = env.bet_acc(new_bet).await.unwrap();
+    assert!(new_bet_acc.is_free_bet);
+    assert_eq!(new_bet_acc.free_bet_side, 0);
+    assert_eq!(new_bet_acc.free_bet_funder, maker.pubkey().to_bytes());
+    assert_eq!(new_bet_acc.rent_payer, taker.pubkey().to_bytes());
+
+    // the free stake can't go to the rent payer
+    env.grade_from_score(1, 1).await;
+    let admin = env.admin.insecure_clone();
+    let push = |destination0: &Pubkey| {
+        client::push(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &new_bet,
+            destination0,
+            &env.taker_tok,
+            &admin.pubkey(),
+            &taker.pubkey(),
+            IDS,
+        ).unwrap()
+    };
+    let to_rent_payer = push(&env.taker_tok);
+    let to_funder = push(&env.maker_tok);
+    let result = process(&mut env.ctx, &[to_rent_payer], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::WrongTokenAccount);
+
+    process(&mut env.ctx, &[to_funder], &[&admin]).await.unwrap();
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS - 100 + 50);
+    assert_eq!(env.balance(env.taker_tok).await, FUNDS);
+    assert_eq!(env.balance(env.vault()).await, 50);
+    assert!(env.bet_acc(new_bet).await.is_none());
+}
+
+#[tokio::test]
+async fn set_delay_holds_back_to_aggregate_cancels() {
+    let mut env = setup(spl_token::id(), None).await;
+
+    let maker = env.maker.insecure_clone();
+    let result = env.set_delay(&maker, 200).await;
+    assert_purebet_error(result, PurebetError::NotAdmin);
+
+    let admin = env.admin.insecure_clone();
+    env.set_delay(&admin, 200).await.unwrap();
+    let account = env.ctx.banks_client.get_account(env.delay_acc).await.unwrap().unwrap();
+    let delay = CancelDelay::unpack(&account.data).unwrap();
+    assert!(delay.is_real);
+    assert_eq!(delay.seconds, 200);
+
+    let bet = env.make(100, 200, true, 0).await;
+    let result = env.cancel(bet, &maker, false, true).await;
+    assert_purebet_error(result, PurebetError::TooEarlyToCancel);
+    assert_eq!(env.balance(env.vault()).await, 100);
+
+    // the admin isn't held to the delay
+    env.cancel(bet, &admin, true, true).await.unwrap();
+    assert!(env.bet_acc(bet).await.is_none());
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn bet_and_delay_accounts_are_not_interchangeable() {
+    let mut env = setup(spl_token::id(), None).await;
+    let admin = env.admin.insecure_clone();
+    env.set_delay(&admin, 0).await.unwrap();
+    let bet = env.make(100, 200, true, 0).await;
+
+    // the delay account passed where the bet goes
+    let delay_acc = env.delay_acc;
+    let result = env.cancel(delay_acc, &admin, true, true).await;
+    assert_purebet_error(result, PurebetError::WrongAccountType);
+
+    // another bet passed where the delay account goes, the bet itself is caught as a duplicate
+    let other_bet = env.make(100, 200, false, 1).await;
+    let ix = client::refund(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.maker_tok,
+        &admin.pubkey(),
+        &env.maker.pubkey(),
+        Some(&other_bet),
+        IDS,
+        0,
+    ).unwrap();
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::WrongAccountType);
+
+    // set_delay can't overwrite a bet
+    let ix = client::set_delay(&env.program_id, &bet, &admin.pubkey(), 30);
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::WrongAccountType);
+    assert_eq!(env.bet_acc(bet).await.unwrap().stake0, 100);
+
+    // an old layout version isn't read as the current one
+    let mut account = env.ctx.banks_client.get_account(bet).await.unwrap().unwrap();
+    account.data[1] = 0;
+    env.ctx.set_account(&bet, &AccountSharedData::from(account));
+    let result = env.cancel(bet, &admin, true, true).await;
+    assert_purebet_error(result, PurebetError::WrongAccountVersion);
+}
+
+#[tokio::test]
+async fn migrate_upgrades_v1_bet_in_place() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+
+    // rewrite the bet as it was stored before matched_at, with rent for the old size only
+    let current = env.bet_acc(bet).await.unwrap();
+    let v1 = BetAccV1 {
+        sport: current.sport,
+        league: current.league,
+        event: current.event,
+        stake0: current.stake0,
+        stake1: current.stake1,
+        wallet0: current.wallet0,
+        rent_payer: current.rent_payer,
+        placed_at: current.placed_at,
+        mint: current.mint,
+        ..Default::default()
+    };
+    let mut data = vec![0; utils::BET_ACC_V1_LEN];
+    v1.pack(&mut data).unwrap();
+    let rent = env.ctx.banks_client.get_rent().await.unwrap();
+    let old_rent = rent.minimum_balance(utils::BET_ACC_V1_LEN);
+    let account = Account {
+        lamports: old_rent,
+        data,
+        owner: env.program_id,
+        ..Account::default()
+    };
+    env.ctx.set_account(&bet, &AccountSharedData::from(account));
+
+    // nothing but migrate reads the old layout
+    let taker = env.taker.insecure_clone();
+    let take = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        100,
+        200,
+        1,
+    );
+    let result = process(&mut env.ctx, std::slice::from_ref(&take), &[&taker]).await;
+    assert_purebet_error(result, PurebetError::WrongAccountVersion);
+
+    // anyone can migrate, the taker pays the extra rent
+    let migrate = client::migrate_bet(&env.program_id, &bet, &taker.pubkey());
+    process(&mut env.ctx, &[migrate], &[&taker]).await.unwrap();
+    let account = env.ctx.banks_client.get_account(bet).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), utils::BET_ACC_LEN);
+    assert_eq!(account.lamports, rent.minimum_balance(utils::BET_ACC_LEN));
+    let migrated = BetAcc::unpack(&account.data).unwrap();
+    assert_eq!(migrated.wallet0, env.maker.pubkey().to_bytes());
+    assert_eq!((migrated.stake0, migrated.stake1), (100, 200));
+    assert_eq!(migrated.placed_at, current.placed_at);
+    assert_eq!(migrated.matched_at, 0);
+
+    // migrating again is a no op
+    let migrate = client::migrate_bet(&env.program_id, &bet, &taker.pubkey());
+    process(&mut env.ctx, &[migrate], &[&taker]).await.unwrap();
+
+    process(&mut env.ctx, &[take], &[&taker]).await.unwrap();
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!(bet_acc.wallet1, taker.pubkey().to_bytes());
+    assert!(bet_acc.matched_at > 0);
+}
+
+#[tokio::test]
+async fn migrate_upgrades_headerless_bet_in_place() {
+    let mut env = setup(spl_token::id(), None).await;
+
+    // headerless bets staked into the old hardcoded pool, registered with the config like any vault
+    let mint = Pubkey::new_from_array(env.pool.mint);
+    let (authority, bump) = utils::find_pool_authority(&mint, &env.program_id);
+    let legacy_pool = Pubkey::new_from_array(utils::LEGACY_POOL);
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    let token_account = spl_token::state::Account {
+        mint,
+        owner: authority,
+        amount: 100,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    spl_token::state::Account::pack(token_account, &mut data).unwrap();
+    let rent = env.ctx.banks_client.get_rent().await.unwrap();
+    let account = Account {
+        lamports: rent.minimum_balance(data.len()),
+        data,
+        owner: spl_token::id(),
+        ..Account::default()
+    };
+    env.ctx.set_account(&legacy_pool, &AccountSharedData::from(account));
+    let admin = env.admin.insecure_clone();
+    let ix = client::set_pool(&env.program_id, &admin.pubkey(), &legacy_pool, bump);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    env.pool.vault = utils::LEGACY_POOL;
+
+    // an open maker order as the first version of the program wrote it, at a non pda address
+    let bet = Pubkey::new_unique();
+    let v0 = BetAccV0 {
+        sport: IDS.sport,
+        league: IDS.league,
+        event: IDS.event,
+        stake0: 100,
+        stake1: 200,
+        wallet0: env.maker.pubkey().to_bytes(),
+        rent_payer: env.maker.pubkey().to_bytes(),
+        placed_at: 1,
+        ..Default::default()
+    };
+    let data = borsh::to_vec(&v0).unwrap();
+    assert_eq!(data.len(), utils::BET_ACC_V0_LEN);
+    let account = Account {
+        lamports: rent.minimum_balance(data.len()),
+        data,
+        owner: env.program_id,
+        ..Account::default()
+    };
+    env.ctx.set_account(&bet, &AccountSharedData::from(account));
+
+    // nothing but migrate reads it, sport 1 passes for the bet type but the league doesn't for a version,
+    // and migrate needs the config to find the mint
+    let maker = env.maker.insecure_clone();
+    let result = env.cancel(bet, &maker, false, false).await;
+    assert_purebet_error(result, PurebetError::WrongAccountVersion);
+    let payer = env.ctx.payer.pubkey();
+    let mut ix = client::migrate_bet(&env.program_id, &bet, &payer);
+    ix.accounts.pop();
+    let result = process(&mut env.ctx, &[ix], &[]).await;
+    assert_purebet_error(result, PurebetError::IncorrectConfig);
+
+    let ix = client::migrate_bet(&env.program_id, &bet, &payer);
+    process(&mut env.ctx, &[ix], &[]).await.unwrap();
+    let account = env.ctx.banks_client.get_account(bet).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), utils::BET_ACC_LEN);
+    assert_eq!(account.lamports, rent.minimum_balance(utils::BET_ACC_LEN));
+    let migrated = BetAcc::unpack(&account.data).unwrap();
+    assert_eq!(migrated.mint, env.pool.mint);
+    assert_eq!(migrated.wallet0, maker.pubkey().to_bytes());
+    assert_eq!((migrated.stake0, migrated.stake1), (100, 200));
+    assert_eq!((migrated.placed_at, migrated.matched_at), (1, 0));
+
+    env.cancel(bet, &maker, false, false).await.unwrap();
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS + 100);
+    assert_eq!(env.balance(legacy_pool).await, 0);
+}
+
+#[tokio::test]
+async fn migrate_finds_the_free_bet_side_of_a_matched_v2_bet() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    env.take(bet).await;
+
+    // v2 bets didn't record the free bet side, settlement used to look it up in the config
+    let current = env.bet_acc(bet).await.unwrap();
+    let bettor = Keypair::new();
+    let v2 = BetAccV2 {
+        sport: current.sport,
+        league: current.league,
+        event: current.event,
+        stake0: current.stake0,
+        stake1: current.stake1,
+        wallet0: bettor.pubkey().to_bytes(),
+        wallet1: current.wallet1,
+        rent_payer: current.rent_payer,
+        is_free_bet: true,
+        placed_at: current.placed_at,
+        mint: current.mint,
+        matched_at: current.matched_at,
+        ..Default::default()
+    };
+    let mut account = env.ctx.banks_client.get_account(bet).await.unwrap().unwrap();
+    account.data = vec![0; utils::BET_ACC_V2_LEN];
+    v2.pack(&mut account.data).unwrap();
+    env.ctx.set_account(&bet, &AccountSharedData::from(account));
+
+    // neither side is a market maker, so there is no telling which one is free
+    let payer = env.ctx.payer.pubkey();
+    let ix = client::migrate_bet(&env.program_id, &bet, &payer);
+    let result = process(&mut env.ctx, &[ix], &[]).await;
+    assert_purebet_error(result, PurebetError::InvalidBetState);
+
+    env.add_taker_as_market_maker().await;
+    let ix = client::migrate_bet(&env.program_id, &bet, &payer);
+    process(&mut env.ctx, &[ix], &[]).await.unwrap();
+    let migrated = env.bet_acc(bet).await.unwrap();
+    assert!(migrated.is_free_bet);
+    assert_eq!(migrated.free_bet_side, 0);
+    assert_eq!(migrated.free_bet_funder, current.rent_payer);
+    assert_eq!(migrated.matched_at, current.matched_at);
+}
+
+#[tokio::test]
+async fn market_result_settles_matched_bets_without_a_signer() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    env.take(bet).await;
+    let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+
+    let result = env.settle(bet, maker_tok, taker_tok).await;
+    assert_purebet_error(result, PurebetError::MarketNotSettled);
+
+    let admin = env.admin.insecure_clone();
+    let result = env.post_result(&admin, MarketResult::Side1Won).await;
+    assert_purebet_error(result, PurebetError::NotOracle);
+    let oracle = env.oracle.insecure_clone();
+    let result = env.post_result(&oracle, MarketResult::Pending).await;
+    assert_purebet_error(result, PurebetError::InvalidMarketStatus);
+    env.grade_from_score(1, 2).await;
+
+    // results are final and the market takes no more orders
+    let result = env.post_result(&oracle, MarketResult::Side0Won).await;
+    assert_purebet_error(result, PurebetError::InvalidMarketStatus);
+    let market = client::market_address(&env.program_id, &IDS);
+    let account = env.ctx.banks_client.get_account(market).await.unwrap().unwrap();
+    let market_acc = Market::unpack(&account.data).unwrap();
+    assert_eq!((market_acc.status, market_acc.result), (MarketStatus::Settled, MarketResult::Side1Won));
+    let maker = env.maker.insecure_clone();
+    let ix = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        IDS,
+        100,
+        200,
+        0,
+        false,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::MarketNotOpen);
+
+    // the winner's destination has to be theirs
+    let result = env.settle(bet, taker_tok, maker_tok).await;
+    assert_purebet_error(result, PurebetError::WrongTokenAccount);
+
+    let maker_lamports = env.lamports(maker.pubkey()).await;
+    let bet_lamports = env.lamports(bet).await;
+    env.settle(bet, maker_tok, taker_tok).await.unwrap();
+    assert_eq!(env.balance(taker_tok).await, FUNDS + 100);
+    assert_eq!(env.balance(maker_tok).await, FUNDS - 100);
+    assert_eq!(env.balance(env.vault()).await, 0);
+    assert_eq!(env.lamports(maker.pubkey()).await, maker_lamports + bet_lamports);
+    assert!(env.bet_acc(bet).await.is_none());
+}
+
+#[tokio::test]
+async fn void_result_refunds_both_sides() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bets = [env.make(100, 200, false, 0).await, env.make(100, 200, false, 1).await];
+    for bet in bets {
+        env.take(bet).await;
+    }
+    let oracle = env.oracle.insecure_clone();
+    env.post_result(&oracle, MarketResult::Void).await.unwrap();
+
+    let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+    for bet in bets {
+        env.settle(bet, maker_tok, taker_tok).await.unwrap();
+    }
+    assert_eq!(env.balance(maker_tok).await, FUNDS);
+    assert_eq!(env.balance(taker_tok).await, FUNDS);
+    assert_eq!(env.balance(env.vault()).await, 0);
+}
+
+#[tokio::test]
+async fn posted_score_grades_every_line_on_the_event() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    env.take(bet).await;
+    let admin = env.admin.insecure_clone();
+    let oracle = env.oracle.insecure_clone();
+    let market = client::market_address(&env.program_id, &IDS);
+    let start_time = {
+        let account = env.ctx.banks_client.get_account(market).await.unwrap().unwrap();
+        Market::unpack(&account.data).unwrap().start_time
+    };
+
+    // home wins 3 to 1
+    let lines = [
+        (0, MarketResult::Side0Won),    // moneyline
+        (1, MarketResult::Side0Won),    // home
+        (2, MarketResult::Side1Won),    // away
+        (3, MarketResult::Side1Won),    // draw
+        (196, MarketResult::Push),      // home -2
+        (197, MarketResult::Side0Won),  // home -1.5
+        (195, MarketResult::Side1Won),  // home -2.5
+        (203, MarketResult::Side0Won),  // home +1.5
+        (1008, MarketResult::Push),     // total 4
+        (1007, MarketResult::Side0Won), // over 3.5
+        (1009, MarketResult::Side1Won), // under 4.5
+    ];
+    for (mkt, _) in &lines[1..] {
+        let ids = BetIds { mkt: *mkt, ..IDS };
+        let ix = client::init_market(&env.program_id, &admin.pubkey(), ids, start_time);
+        process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    }
+    let props = BetIds { player: 1, ..IDS };
+    let ix = client::init_market(&env.program_id, &admin.pubkey(), props, start_time);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+
+    let event: EventIds = IDS.event_ids();
+    let ix = client::grade_market(&env.program_id, IDS);
+    let result = process(&mut env.ctx, &[ix], &[]).await;
+    assert_purebet_error(result, PurebetError::IncorrectOwner);
+    let ix = client::post_score(&env.program_id, &admin.pubkey(), event, 3, 1);
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::NotOracle);
+    let ix = client::post_score(&env.program_id, &oracle.pubkey(), event, 3, 1);
+    process(&mut env.ctx, &[ix], &[&oracle]).await.unwrap();
+    // scores are final
+    let ix = client::post_score(&env.program_id, &oracle.pubkey(), event, 1, 3);
+    let result = process(&mut env.ctx, &[ix], &[&oracle]).await;
+    assert_purebet_error(result, PurebetError::ScoreAlreadyPosted);
+
+    for (mkt, expected) in lines {
+        let ids = BetIds { mkt, ..IDS };
+        let ix = client::grade_market(&env.program_id, ids);
+        process(&mut env.ctx, &[ix], &[]).await.unwrap();
+        let account = env.ctx.banks_client.get_account(client::market_address(&env.program_id, &ids)).await.unwrap().unwrap();
+        let market_acc = Market::unpack(&account.data).unwrap();
+        assert_eq!((mkt, market_acc.status, market_acc.result), (mkt, MarketStatus::Settled, expected));
+    }
+    let ix = client::grade_market(&env.program_id, IDS);
+    let result = process(&mut env.ctx, &[ix], &[]).await;
+    assert_purebet_error(result, PurebetError::InvalidMarketStatus);
+    let ix = client::grade_market(&env.program_id, props);
+    let result = process(&mut env.ctx, &[ix], &[]).await;
+    assert_purebet_error(result, PurebetError::UngradableMarket);
+
+    // bets settle against the graded market like a posted result
+    let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+    env.settle(bet, maker_tok, taker_tok).await.unwrap();
+    assert_eq!(env.balance(maker_tok).await, FUNDS + 200);
+}
+
+#[tokio::test]
+async fn oracle_can_only_void_markets_graded_from_the_score() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    env.take(bet).await;
+
+    // a posted result that disagrees with the score would pay these bets out differently
+    let oracle = env.oracle.insecure_clone();
+    for result in [MarketResult::Side0Won, MarketResult::Push] {
+        let result = env.post_result(&oracle, result).await;
+        assert_purebet_error(result, PurebetError::InvalidMarketResult);
+    }
+    env.grade_from_score(2, 1).await;
+    let market = client::market_address(&env.program_id, &IDS);
+    let account = env.ctx.banks_client.get_account(market).await.unwrap().unwrap();
+    assert_eq!(Market::unpack(&account.data).unwrap().result, MarketResult::Side0Won);
+
+    let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+    env.settle(bet, maker_tok, taker_tok).await.unwrap();
+    assert_eq!(env.balance(maker_tok).await, FUNDS + 200);
+    assert_eq!(env.balance(taker_tok).await, FUNDS - 200);
+}
+
+#[tokio::test]
+async fn quarter_lines_grade_and_pay_half_wins() {
+    let mut env = setup(spl_token::id(), None).await;
+    let admin = env.admin.insecure_clone();
+    let oracle = env.oracle.insecure_clone();
+    let start_time = {
+        let market = client::market_address(&env.program_id, &IDS);
+        let account = env.ctx.banks_client.get_account(market).await.unwrap().unwrap();
+        Market::unpack(&account.data).unwrap().start_time
+    };
+
+    // home wins 2 to 1
+    let lines = [
+        (MarketKind::AsianSpread(-1), MarketResult::Side0Won),     // home -0.25
+        (MarketKind::AsianSpread(-3), MarketResult::Side0HalfWon), // home -0.75
+        (MarketKind::AsianSpread(-5), MarketResult::Side1HalfWon), // home -1.25
+        (MarketKind::AsianSpread(3), MarketResult::Side0Won),      // home +0.75
+        (MarketKind::AsianTotal(11), MarketResult::Side0HalfWon),  // 2.75
+        (MarketKind::AsianTotal(13), MarketResult::Side1HalfWon),  // 3.25
+        (MarketKind::AsianTotal(15), MarketResult::Side1Won),      // 3.75
+    ];
+    for (kind, _) in lines {
+        let ids = BetIds { mkt: kind.encode().unwrap(), ..IDS };
+        let ix = client::init_market(&env.program_id, &admin.pubkey(), ids, start_time);
+        process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    }
+
+    // maker backs home -1.25 with 100, taker takes away +1.25 with 200
+    let ids = BetIds { mkt: MarketKind::AsianSpread(-5).encode().unwrap(), ..IDS };
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+    let bet = client::bet_address(&env.program_id, &ids, &maker.pubkey(), 0);
+    let make = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        ids,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    let take = client::taker(&env.program_id, &env.pool, &env.token_program, &bet, &env.taker_tok, &taker.pubkey(), ids, 100, 200, 1);
+    process(&mut env.ctx, &[make, take], &[&maker, &taker]).await.unwrap();
+
+    let ix = client::post_score(&env.program_id, &oracle.pubkey(), IDS.event_ids(), 2, 1);
+    process(&mut env.ctx, &[ix], &[&oracle]).await.unwrap();
+    for (kind, expected) in lines {
+        let ids = BetIds { mkt: kind.encode().unwrap(), ..IDS };
+        let ix = client::grade_market(&env.program_id, ids);
+        process(&mut env.ctx, &[ix], &[]).await.unwrap();
+        let account = env.ctx.banks_client.get_account(client::market_address(&env.program_id, &ids)).await.unwrap().unwrap();
+        assert_eq!((kind, Market::unpack(&account.data).unwrap().result), (kind, expected));
+    }
+
+    // the taker's half on -1 pushes and the half on -1.5 wins, so they get 200 back and half the maker's 100
+    let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+    let ix = client::settle(&env.program_id, &env.pool, &env.token_program, &bet, &maker_tok, &taker_tok, &maker.pubkey(), ids).unwrap();
+    process(&mut env.ctx, &[ix], &[]).await.unwrap();
+    assert_eq!(env.balance(maker_tok).await, FUNDS - 50);
+    assert_eq!(env.balance(taker_tok).await, FUNDS + 50);
+    assert_eq!(env.balance(env.vault()).await, 0);
+
+    // the oracle can't post a half win on a line that can't be split, like a player's moneyline
+    let props = BetIds { player: u32::from_le_bytes(*b"LJAM"), ..IDS };
+    let ix = client::init_market(&env.program_id, &admin.pubkey(), props, start_time);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    let ix = client::post_result(&env.program_id, &oracle.pubkey(), props, MarketResult::Side1HalfWon);
+    let result = process(&mut env.ctx, &[ix], &[&oracle]).await;
+    assert_purebet_error(result, PurebetError::InvalidMarketResult);
+}
+
+#[tokio::test]
+async fn migrate_config_adds_a_blank_oracle() {
+    let mut env = setup(spl_token::id(), None).await;
+    let config_key = client::config_address(&env.program_id);
+    let mut account = env.ctx.banks_client.get_account(config_key).await.unwrap().unwrap();
+    let config = Config::unpack(&account.data).unwrap();
+    let v1 = ConfigV1 {
+        is_initialized: true,
+        admin: config.admin,
+        market_makers: config.market_makers,
+        pools: config.pools,
+        odds_tolerance_bps: config.odds_tolerance_bps,
+    };
+    account.data = vec![0; utils::CONFIG_V1_LEN];
+    v1.pack(&mut account.data).unwrap();
+    env.ctx.set_account(&config_key, &AccountSharedData::from(account));
+
+    // nothing reads the old layout until it is migrated
+    let oracle = env.oracle.insecure_clone();
+    let result = env.post_result(&oracle, MarketResult::Push).await;
+    assert_purebet_error(result, PurebetError::WrongAccountVersion);
+
+    let payer = env.ctx.payer.pubkey();
+    let ix = client::migrate_config(&env.program_id, &payer);
+    process(&mut env.ctx, &[ix], &[]).await.unwrap();
+    let account = env.ctx.banks_client.get_account(config_key).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), utils::CONFIG_LEN);
+    let migrated = Config::unpack(&account.data).unwrap();
+    assert_eq!(migrated.admin, config.admin);
+    assert_eq!(migrated.pools[0].vault, env.pool.vault);
+    assert!(utils::blank_wallet(migrated.oracle));
+
+    let result = env.post_result(&oracle, MarketResult::Void).await;
+    assert_purebet_error(result, PurebetError::NotOracle);
+    let admin = env.admin.insecure_clone();
+    let ix = client::set_oracle(&env.program_id, &admin.pubkey(), &oracle.pubkey());
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    env.post_result(&oracle, MarketResult::Void).await.unwrap();
+}
+
+#[tokio::test]
+async fn transfer_fee_mint_credits_pool_with_full_stake() {
+    // 1% fee on every transfer
+    let mut env = setup(spl_token_2022::id(), Some(100)).await;
+    let bet = env.make(10_000, 20_000, false, 0).await;
+
+    assert_eq!(env.balance(env.vault()).await, 10_000);
+    assert_eq!(env.bet_acc(bet).await.unwrap().stake0, 10_000);
+    // the maker covers the fee on top of their stake
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS - 10_102);
+
+    let taker = env.taker.insecure_clone();
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        10_000,
+        20_000,
+        1,
+    );
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+    assert_eq!(env.balance(env.vault()).await, 30_000);
+}
+
+#[tokio::test]
+async fn init_vault_whitelists_mint_with_canonical_bump() {
+    for (token_program, fee_bps) in [(spl_token::id(), None), (spl_token_2022::id(), Some(100))] {
+        let mut env = setup(token_program, fee_bps).await;
+        let mint = Pubkey::new_from_array(env.pool.mint);
+        let (authority, bump) = utils::find_pool_authority(&mint, &env.program_id);
+        assert_eq!(env.pool.vault, client::vault_address(&env.program_id, &mint).to_bytes());
+
+        let config = env.ctx.banks_client.get_account(client::config_address(&env.program_id)).await.unwrap().unwrap();
+        let config = Config::unpack(&config.data).unwrap();
+        let pool = config.pool_for_mint(env.pool.mint).unwrap();
+        assert_eq!((pool.vault, pool.bump), (env.pool.vault, bump));
+
+        let vault = env.ctx.banks_client.get_account(env.vault()).await.unwrap().unwrap();
+        assert_eq!(vault.owner, token_program);
+        let vault = StateWithExtensions::<TokenAccount>::unpack(&vault.data).unwrap();
+        assert_eq!(vault.base.owner, authority);
+        assert_eq!(vault.base.mint, mint);
+
+        // only the admin can create vaults
+        let maker = env.maker.insecure_clone();
+        let ix = client::init_vault(&env.program_id, &maker.pubkey(), &mint, &token_program);
+        let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+        assert_purebet_error(result, PurebetError::NotAdmin);
+
+        // stakes still go out through the stored bump
+        let bet = env.make(100, 200, false, 0).await;
+        let maker = env.maker.insecure_clone();
+        env.cancel(bet, &maker, false, false).await.unwrap();
+        assert_eq!(env.balance(env.vault()).await, 0);
+    }
+}
+
+#[tokio::test]
+async fn set_pool_moves_mint_to_an_existing_vault() {
+    let mut env = setup(spl_token::id(), None).await;
+    let mint = Pubkey::new_from_array(env.pool.mint);
+    let (authority, bump) = utils::find_pool_authority(&mint, &env.program_id);
+    let vault = create_token_account(&mut env.ctx, &env.token_program, &mint, &authority, &[]).await;
+
+    // the bump has to derive the pda owning the vault
+    let admin = env.admin.insecure_clone();
+    let ix = client::set_pool(&env.program_id, &admin.pubkey(), &vault, bump.wrapping_sub(1));
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::IncorrectPoolAuthority);
+
+    let ix = client::set_pool(&env.program_id, &admin.pubkey(), &vault, bump);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+    env.pool.vault = vault.to_bytes();
+
+    let bet = env.make(100, 200, false, 0).await;
+    assert_eq!(env.balance(vault).await, 100);
+    let maker = env.maker.insecure_clone();
+    env.cancel(bet, &maker, false, false).await.unwrap();
+    assert_eq!(env.balance(vault).await, 0);
+}
+
+#[tokio::test]
+async fn maker_rejects_codes_that_dont_decode() {
+    let mut env = setup(spl_token::id(), None).await;
+    let maker = env.maker.insecure_clone();
+    // an unassigned mkt code and a player with no last name
+    for ids in [BetIds { mkt: 398, ..IDS }, BetIds { player: 1, ..IDS }] {
+        let ix = client::maker(
+            &env.program_id,
+            &env.pool,
+            &env.token_program,
+            &env.maker_tok,
+            &maker.pubkey(),
+            &maker.pubkey(),
+            &maker.pubkey(),
+            ids,
+            100,
+            200,
+            0,
+            false,
+            0,
+        );
+        let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+        assert_purebet_error(result, PurebetError::InvalidMarketCode);
+    }
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn maker_rejects_unknown_pool() {
+    let mut env = setup(spl_token::id(), None).await;
+    let mut pool = env.pool;
+    pool.vault = env.taker_tok.to_bytes();
+    let maker = env.maker.insecure_clone();
+    let ix = client::maker(
+        &env.program_id,
+        &pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        IDS,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::IncorrectPool);
+}
+
+// extreme u64 stakes and timestamps, everything has to either work exactly or fail with an error
+
+#[tokio::test]
+async fn extreme_stakes_through_maker_and_takers() {
+    let mut env = setup(spl_token::id(), None).await;
+    let big = u64::MAX - FUNDS;
+    let payer = env.ctx.payer.pubkey();
+    let mint = Pubkey::new_from_array(env.pool.mint);
+    let ix = spl_token_2022::instruction::mint_to(&env.token_program, &mint, &env.maker_tok, &payer, &[], big - FUNDS)
+        .unwrap();
+    process(&mut env.ctx, &[ix], &[]).await.unwrap();
+
+    let bet = env.make(big, u64::MAX, false, 0).await;
+    assert_eq!(env.balance(env.vault()).await, big);
+    let taker = env.taker.insecure_clone();
+
+    // risking u64::MAX to win u64::MAX is better than the maker offers
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        u64::MAX,
+        u64::MAX,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::OddsTooHigh);
+
+    let ix = client::taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        IDS,
+        1,
+        FUNDS,
+        1,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&taker]).await;
+    assert_purebet_error(result, PurebetError::InsufficientStake);
+
+    let ix = client::partial_taker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.taker_tok,
+        &taker.pubkey(),
+        &taker.pubkey(),
+        &env.maker_tok,
+        IDS,
+        0,
+        FUNDS,
+        1,
+        0,
+        0,
+    ).unwrap();
+    process(&mut env.ctx, &[ix], &[&taker]).await.unwrap();
+
+    let matched = (FUNDS as u128 * big as u128 / u64::MAX as u128) as u64;
+    let maker_left = ((u64::MAX - FUNDS) as u128 * big as u128 / u64::MAX as u128) as u64;
+    let dust = big - matched - maker_left;
+    let new_bet = client::bet_address(&env.program_id, &IDS, &taker.pubkey(), 0);
+    let new_bet_acc = env.bet_acc(new_bet).await.unwrap();
+    assert_eq!((new_bet_acc.stake0, new_bet_acc.stake1), (matched, FUNDS));
+    let bet_acc = env.bet_acc(bet).await.unwrap();
+    assert_eq!((bet_acc.stake0, bet_acc.stake1), (maker_left, u64::MAX - FUNDS));
+    assert_eq!(env.balance(env.vault()).await, big - dust + FUNDS);
+    assert_eq!(env.balance(env.maker_tok).await, dust);
+}
+
+#[tokio::test]
+async fn grade_with_overflowing_payout_fails() {
+    let mut env = setup(spl_token::id(), None).await;
+    let bet = env.make(100, 200, false, 0).await;
+    let mut bet_acc = env.bet_acc(bet).await.unwrap();
+    bet_acc.wallet1 = env.taker.pubkey().to_bytes();
+    bet_acc.stake0 = u64::MAX;
+    bet_acc.stake1 = 1;
+    env.overwrite_bet(bet, &bet_acc).await;
+    env.grade_from_score(2, 1).await;
+
+    let admin = env.admin.insecure_clone();
+    let ix = client::grade(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &bet,
+        &env.maker_tok,
+        &admin.pubkey(),
+        &env.maker.pubkey(),
+        IDS,
+        0,
+    ).unwrap();
+    let result = process(&mut env.ctx, &[ix], &[&admin]).await;
+    assert_purebet_error(result, PurebetError::ArithmeticOverflow);
+}
+
+// bet matched on a player's quarter line, so the oracle can post any result for it, with the whole
+// u64::MAX token supply moved into the vault and the bettors' accounts left empty
+async fn setup_extreme_bet(result: MarketResult, stake0: u64, stake1: u64) -> (Env, Pubkey, BetIds) {
+    let mut env = setup(spl_token::id(), None).await;
+    let (admin, oracle) = (env.admin.insecure_clone(), env.oracle.insecure_clone());
+    let (maker, taker) = (env.maker.insecure_clone(), env.taker.insecure_clone());
+    let start_time = {
+        let market = client::market_address(&env.program_id, &IDS);
+        let account = env.ctx.banks_client.get_account(market).await.unwrap().unwrap();
+        Market::unpack(&account.data).unwrap().start_time
+    };
+    let ids = BetIds {
+        mkt: MarketKind::AsianSpread(-3).encode().unwrap(),
+        player: u32::from_le_bytes(*b"LJAM"),
+        ..IDS
+    };
+    let ix = client::init_market(&env.program_id, &admin.pubkey(), ids, start_time);
+    process(&mut env.ctx, &[ix], &[&admin]).await.unwrap();
+
+    let bet = client::bet_address(&env.program_id, &ids, &maker.pubkey(), 0);
+    let make = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        ids,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    let take = client::taker(&env.program_id, &env.pool, &env.token_program, &bet, &env.taker_tok, &taker.pubkey(), ids, 100, 200, 1);
+    process(&mut env.ctx, &[make, take], &[&maker, &taker]).await.unwrap();
+    let ix = client::post_result(&env.program_id, &oracle.pubkey(), ids, result);
+    process(&mut env.ctx, &[ix], &[&oracle]).await.unwrap();
+
+    let mint = Pubkey::new_from_array(env.pool.mint);
+    let vault = env.vault();
+    let mut ixs = vec![spl_token_2022::instruction::mint_to(&env.token_program, &mint, &vault, &env.ctx.payer.pubkey(), &[], u64::MAX - 2 * FUNDS).unwrap()];
+    for (tok, owner, amount) in [(env.maker_tok, &maker, FUNDS - 100), (env.taker_tok, &taker, FUNDS - 200)] {
+        let ix = spl_token_2022::instruction::transfer_checked(&env.token_program, &tok, &mint, &vault, &owner.pubkey(), &[], amount, 6)
+            .unwrap();
+        ixs.push(ix);
+    }
+    process(&mut env.ctx, &ixs, &[&maker, &taker]).await.unwrap();
+    assert_eq!(env.balance(vault).await, u64::MAX);
+
+    let mut bet_acc = env.bet_acc(bet).await.unwrap();
+    bet_acc.stake0 = stake0;
+    bet_acc.stake1 = stake1;
+    env.overwrite_bet(bet, &bet_acc).await;
+    (env, bet, ids)
+}
+
+#[tokio::test]
+async fn push_and_void_with_extreme_stakes() {
+    let big = u64::MAX - 200;
+    for (result, admin_push) in [(MarketResult::Push, true), (MarketResult::Void, true), (MarketResult::Push, false), (MarketResult::Void, false)] {
+        let (mut env, bet, ids) = setup_extreme_bet(result, big, 200).await;
+        let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+        let admin = env.admin.insecure_clone();
+        let maker = env.maker.pubkey();
+        let (ix, signers) = if admin_push {
+            let ix = client::push(&env.program_id, &env.pool, &env.token_program, &bet, &maker_tok, &taker_tok, &admin.pubkey(), &maker, ids);
+            (ix.unwrap(), vec![&admin])
+        } else {
+            let ix = client::settle(&env.program_id, &env.pool, &env.token_program, &bet, &maker_tok, &taker_tok, &maker, ids);
+            (ix.unwrap(), vec![])
+        };
+        process(&mut env.ctx, &[ix], &signers).await.unwrap();
+        assert_eq!(env.balance(maker_tok).await, big);
+        assert_eq!(env.balance(taker_tok).await, 200);
+        assert_eq!(env.balance(env.vault()).await, 0);
+    }
+}
+
+#[tokio::test]
+async fn half_wins_with_extreme_stakes() {
+    let half = u64::MAX - 100;
+    let cases = [
+        // the winner gets the whole supply less the loser's pushed half
+        (MarketResult::Side0HalfWon, (u64::MAX - 200, 200), Some((half, 100))),
+        (MarketResult::Side1HalfWon, (200, u64::MAX - 200), Some((100, half))),
+        // the winner's stake plus the loser's lost half doesn't fit in a u64
+        (MarketResult::Side0HalfWon, (u64::MAX, 2), None),
+        (MarketResult::Side1HalfWon, (2, u64::MAX), None),
+    ];
+    for (result, (stake0, stake1), payouts) in cases {
+        let (mut env, bet, ids) = setup_extreme_bet(result, stake0, stake1).await;
+        let (maker_tok, taker_tok) = (env.maker_tok, env.taker_tok);
+        let maker = env.maker.pubkey();
+        let ix = client::settle(&env.program_id, &env.pool, &env.token_program, &bet, &maker_tok, &taker_tok, &maker, ids).unwrap();
+        let result = process(&mut env.ctx, &[ix], &[]).await;
+        match payouts {
+            Some((payout0, payout1)) => {
+                result.unwrap();
+                assert_eq!(env.balance(maker_tok).await, payout0);
+                assert_eq!(env.balance(taker_tok).await, payout1);
+                assert_eq!(env.balance(env.vault()).await, 0);
+            }
+            None => {
+                assert_purebet_error(result, PurebetError::ArithmeticOverflow);
+                assert_eq!(env.balance(env.vault()).await, u64::MAX);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn cancel_delay_with_extreme_placed_at() {
+    let mut env = setup(spl_token::id(), None).await;
+    let admin = env.admin.insecure_clone();
+    env.set_delay(&admin, u8::MAX).await.unwrap();
+
+    let bet = env.make(100, 200, true, 0).await;
+    let mut bet_acc = env.bet_acc(bet).await.unwrap();
+    bet_acc.placed_at = u64::MAX;
+    env.overwrite_bet(bet, &bet_acc).await;
+
+    // placed_at + delay doesn't fit in a u64
+    let maker = env.maker.insecure_clone();
+    let result = env.cancel(bet, &maker, false, true).await;
+    assert_purebet_error(result, PurebetError::ArithmeticOverflow);
+
+    env.cancel(bet, &admin, true, true).await.unwrap();
+    assert_eq!(env.balance(env.maker_tok).await, FUNDS);
+}
+
+#[tokio::test]
+async fn maker_with_clock_before_1970_fails() {
+    let mut env = setup(spl_token::id(), None).await;
+    let mut clock: Clock = env.ctx.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = -1;
+    env.ctx.set_sysvar(&clock);
+
+    let maker = env.maker.insecure_clone();
+    let ix = client::maker(
+        &env.program_id,
+        &env.pool,
+        &env.token_program,
+        &env.maker_tok,
+        &maker.pubkey(),
+        &maker.pubkey(),
+        &maker.pubkey(),
+        IDS,
+        100,
+        200,
+        0,
+        false,
+        0,
+    );
+    let result = process(&mut env.ctx, &[ix], &[&maker]).await;
+    assert_purebet_error(result, PurebetError::ArithmeticOverflow);
+}